@@ -0,0 +1,111 @@
+//! Runs the swapchain acquire and present path without a display.
+//!
+//! Works on GPU-less machines with lavapipe, e.g. for CI.
+//! `tests/headless.rs` runs it as a test.
+
+use gallium::{
+    AcquireResult, AttachmentDesc, AttachmentLoadOp, ClearValue, Extent2D, GPUQueueInfo,
+    ImageLayout, Instance, InstanceDesc, PipelineStageFlags, RenderPassDesc, SubPass, SubmitDesc,
+    Surface, SwapchainDesc, ValidationDesc,
+};
+
+const FRAMES: usize = 10;
+
+// Public so that the test can call it.
+pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let instance = Instance::new_headless(InstanceDesc {
+        app_name: "Headless".to_owned(),
+        validation: Some(ValidationDesc::default()),
+    })?;
+    let v_gpu = instance.enumerate_gpu()?;
+    let mut gpu_index = 0;
+    let mut info = GPUQueueInfo::default();
+    for (i, g) in v_gpu.iter().enumerate() {
+        if g.is_support_graphics(&instance, &mut info) {
+            println!("Supported! Name: {}", g.name());
+            gpu_index = i;
+        }
+    }
+    let gpu = &v_gpu[gpu_index];
+    let device = instance.create_device(gpu, info)?;
+    let queue = device.get_queue(info)?;
+
+    let surface = Surface::create_headless(&instance)?;
+    let mut swapchain = device.create_swapchain_with_desc(
+        &instance,
+        gpu,
+        &surface,
+        &SwapchainDesc {
+            extent: Some(Extent2D {
+                width: 640,
+                height: 480,
+            }),
+            ..Default::default()
+        },
+    )?;
+
+    let render_pass = device.create_render_pass(&RenderPassDesc {
+        attachments: vec![AttachmentDesc {
+            format: swapchain.format(),
+            load_op: AttachmentLoadOp::CLEAR,
+            final_layout: ImageLayout::PRESENT_SRC_KHR,
+            ..Default::default()
+        }],
+        subpasses: vec![SubPass::new()],
+    })?;
+
+    let mut frames = device.create_frame_ring(&queue, 2)?;
+    for i in 0..FRAMES {
+        // Exercises recreation halfway through.
+        if i == FRAMES / 2 {
+            swapchain.recreate(Extent2D {
+                width: 320,
+                height: 240,
+            })?;
+        }
+        let extent = swapchain.extent();
+        let frame_buffers = swapchain
+            .image_views()
+            .iter()
+            .map(|view| {
+                view.create_frame_buffer(&device, &render_pass, extent.width, extent.height)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let frame = frames.next_frame()?;
+        let index =
+            match device.acquire_next_image(&swapchain, Some(frame.image_available()), None)? {
+                AcquireResult::Ok(i) | AcquireResult::Suboptimal(i) => i,
+                AcquireResult::OutOfDate => return Err("headless swapchain is out of date".into()),
+            };
+        let gallium = frame.gallium();
+        gallium.begin_draw(&device)?;
+        gallium.begin_render_pass(
+            &device,
+            &frame_buffers[index],
+            &render_pass,
+            extent.width,
+            extent.height,
+            &[ClearValue::Color([0.0, 0.0, 0.0, 1.0])],
+        );
+        gallium.end_render_pass(&device);
+        gallium.end_draw(&device)?;
+        frame.submit(
+            &device,
+            &queue,
+            SubmitDesc::new()
+                .wait(
+                    frame.image_available(),
+                    PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                )
+                .signal(frame.render_finished()),
+        )?;
+        let result = queue.present(&swapchain, index, &[frame.render_finished()])?;
+        println!("Frame {}: image {}, {:?}", i, index, result);
+        // The frame buffers are dropped at the end of the iteration.
+        device.wait_idle()?;
+    }
+    device.wait_idle()?;
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use gallium::{
+    Format, GPUQueueInfo, GraphicsPipelineDesc, Instance, InstanceDesc, OffscreenTargetDesc,
+    ShaderKind, Spirv, ValidationDesc,
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let instance = Instance::new(InstanceDesc {
+        app_name: "Offscreen".to_owned(),
+        validation: Some(ValidationDesc::default()),
+    })?;
+    let v_gpu = instance.enumerate_gpu()?;
+    let mut gpu_index = 0;
+    let mut info = GPUQueueInfo::default();
+    for (i, g) in v_gpu.iter().enumerate() {
+        if g.is_support_graphics(&instance, &mut info) {
+            println!("Supported! Name: {}", g.name());
+            gpu_index = i;
+        }
+    }
+    let gpu = &v_gpu[gpu_index];
+    let device = instance.create_device(gpu, info)?;
+    let queue = device.get_queue(info)?;
+
+    let target = device.create_offscreen_target(
+        &queue,
+        &OffscreenTargetDesc::new(640, 480).format(Format::R8G8B8A8_SRGB),
+    )?;
+
+    let fragment_shader = device.create_shader_module(
+        Spirv::new("examples/shader/shader.frag.spv")?,
+        ShaderKind::Fragment,
+    )?;
+    let vertex_shader = device.create_shader_module(
+        Spirv::new("examples/shader/shader.vert.spv")?,
+        ShaderKind::Vertex,
+    )?;
+    let pipeline = target.render_pass().create_graphics_pipeline(
+        &device,
+        &[fragment_shader, vertex_shader],
+        &GraphicsPipelineDesc::new(),
+    )?;
+
+    target.render(|gallium| {
+        gallium.bind_pipeline(&device, &pipeline);
+        gallium.draw(&device, 3, 1, 0, 0);
+    })?;
+    target.save_png("offscreen.png")?;
+    Ok(())
+}
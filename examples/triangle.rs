@@ -1,16 +1,16 @@
 use std::{fs::File, io::BufWriter};
 
-use gallium::{GPUQueueInfo, Instance, InstanceDesc, ShaderKind, Spirv, SubPass};
+use gallium::{
+    AttachmentDesc, AttachmentLoadOp, ClearValue, GPUQueueInfo, ImageLayout, Instance,
+    InstanceDesc, RenderPassDesc, ShaderKind, Spirv, SubPass, ValidationDesc,
+};
 
-fn main() {
-    let instance = match Instance::new(InstanceDesc {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let instance = Instance::new(InstanceDesc {
         app_name: "Triangle".to_owned(),
-    }) {
-        Ok(i) => i,
-        Err(e) => panic!("{:?}", e),
-    };
-    let v_gpu = instance.enumerate_gpu().unwrap();
-    let mut index = 0;
+        validation: Some(ValidationDesc::default()),
+    })?;
+    let v_gpu = instance.enumerate_gpu()?;
     let mut gpu_index = 0;
     let mut info = GPUQueueInfo::default();
     for (i, g) in v_gpu.iter().enumerate() {
@@ -20,55 +20,61 @@ fn main() {
         }
     }
     let gpu = &v_gpu[gpu_index];
-    let device = instance.create_device(gpu, info).unwrap();
-    let queue = device.get_queue(info);
-    let gallium = device.create_gallium(&queue).unwrap();
+    let device = instance.create_device(gpu, info)?;
+    let queue = device.get_queue(info)?;
+    let gallium = device.create_gallium(&queue)?;
 
-    let image = device.create_image(&instance, gpu, 640, 480).unwrap();
-    let image_view = image.create_image_view(&device).unwrap();
+    let image = device.create_image(&instance, gpu, 640, 480)?;
+    let image_view = image.create_image_view(&device)?;
 
-    let subpasses = vec![SubPass::new()];
-    let render_pass = device.create_render_pass(&subpasses).unwrap();
+    let render_pass = device.create_render_pass(&RenderPassDesc {
+        attachments: vec![AttachmentDesc {
+            load_op: AttachmentLoadOp::CLEAR,
+            ..Default::default()
+        }],
+        subpasses: vec![SubPass::new()],
+    })?;
 
-    let frame_buffer = image_view
-        .create_frame_buffer(&device, &render_pass, 640, 480)
-        .unwrap();
+    let frame_buffer = image_view.create_frame_buffer(&device, &render_pass, 640, 480)?;
 
-    let fragment_shader = device
-        .create_shader_module(
-            Spirv::new("examples/shader/shader.frag.spv"),
-            ShaderKind::Fragment,
-        )
-        .unwrap();
-    let vertex_shader = device
-        .create_shader_module(
-            Spirv::new("examples/shader/shader.vert.spv"),
-            ShaderKind::Vertex,
-        )
-        .unwrap();
-    let pipeline = render_pass
-        .create_pipeline(&image, &device, &[fragment_shader, vertex_shader])
-        .unwrap();
+    let fragment_shader = device.create_shader_module(
+        Spirv::new("examples/shader/shader.frag.spv")?,
+        ShaderKind::Fragment,
+    )?;
+    let vertex_shader = device.create_shader_module(
+        Spirv::new("examples/shader/shader.vert.spv")?,
+        ShaderKind::Vertex,
+    )?;
+    let pipeline =
+        render_pass.create_pipeline(&image, &device, &[fragment_shader, vertex_shader])?;
 
-    gallium.begin_draw(&device);
-    gallium.begin_render_pass(&device, &frame_buffer, &render_pass, 640, 480);
-    gallium.end_render_pass(&device);
+    gallium.begin_draw(&device)?;
+    gallium.begin_render_pass(
+        &device,
+        &frame_buffer,
+        &render_pass,
+        640,
+        480,
+        &[ClearValue::Color([0.0, 0.0, 0.0, 1.0])],
+    );
     gallium.bind_pipeline(&device, &pipeline[0]);
     gallium.draw(&device, 3, 1, 0, 0);
-    gallium.end_draw(&device);
-    device.dispatch_to_queue(&gallium, &queue);
+    gallium.end_render_pass(&device);
+    gallium.end_draw(&device)?;
+    let fence = device.create_fence()?;
+    device.dispatch_to_queue_with_fence(&gallium, &queue, &fence)?;
+    fence.wait(None)?;
 
     let path = "example.png";
-    let file = File::create(path).unwrap();
+    let file = File::create(path)?;
     let w = &mut BufWriter::new(file);
 
     let mut encoder = png::Encoder::new(w, 640, 480);
     encoder.set_color(png::ColorType::Rgba);
     encoder.set_depth(png::BitDepth::Eight);
 
-    let mut writer = encoder.write_header().unwrap();
+    let mut writer = encoder.write_header()?;
 
-    let data = image.map_memory(&device);
-    let slice: &[u8] = unsafe { std::slice::from_raw_parts(data as *const u8, 1228800) };
-    writer.write_image_data(&slice).unwrap();
+    writer.write_image_data(&image.read_pixels(&device, &queue, ImageLayout::GENERAL)?)?;
+    Ok(())
 }
@@ -1,9 +1,8 @@
-use std::{fs::File, io::BufWriter};
-
 use gallium::{
-    GPUQueueInfo, Instance, InstanceDesc, ShaderKind, Spirv, SubPass, Surface, HINSTANCE, HWND,
+    AcquireResult, AttachmentDesc, AttachmentLoadOp, ClearValue, Device, Extent2D, FrameBuffer,
+    GPUQueueInfo, GraphicsPipelineDesc, ImageLayout, Instance, InstanceDesc, PipelineStageFlags,
+    RenderPass, RenderPassDesc, ShaderKind, Spirv, SubPass, SubmitDesc, Surface, Swapchain,
 };
-use raw_window_handle::HasRawWindowHandle;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::EventLoop,
@@ -23,13 +22,13 @@ fn main() {
         &window,
         InstanceDesc {
             app_name: "Triangle".to_owned(),
+            ..Default::default()
         },
     ) {
         Ok(i) => i,
         Err(e) => panic!("{:?}", e),
     };
     let v_gpu = instance.enumerate_gpu().unwrap();
-    let mut index = 0;
     let mut gpu_index = 0;
     let mut info = GPUQueueInfo::default();
     for (i, g) in v_gpu.iter().enumerate() {
@@ -40,55 +39,49 @@ fn main() {
     }
     let gpu = &v_gpu[gpu_index];
     let device = instance.create_device(gpu, info).unwrap();
-    let queue = device.get_queue(info);
-    let gallium = device.create_gallium(&queue).unwrap();
+    let queue = device.get_queue(info).unwrap();
 
-    let handle = window.raw_window_handle();
-    let surface = match handle {
-        raw_window_handle::RawWindowHandle::Win32(handle) => Surface::create_for_win32(
-            &instance,
-            handle.hwnd as HWND,
-            handle.hinstance as HINSTANCE,
-        ),
-        _ => panic!("Not supported"),
-    };
-    let swapchain = device
-        .create_swapchain(&instance, &device, &gpu, &surface)
+    let surface = Surface::new(&instance, &window).unwrap();
+    let mut swapchain = device
+        .create_swapchain(&instance, &device, gpu, &surface)
         .unwrap();
 
-    let image = device.create_image(&instance, gpu, 640, 480).unwrap();
-    let image_view = image.create_image_view(&device).unwrap();
-    let image_view = swapchain.get_image(&device).unwrap();
-
-    let subpasses = vec![SubPass::new()];
-    let render_pass = device.create_render_pass(&subpasses).unwrap();
-
-    let mut frame_buffers = vec![];
+    let render_pass = device
+        .create_render_pass(&RenderPassDesc {
+            attachments: vec![AttachmentDesc {
+                format: swapchain.format(),
+                load_op: AttachmentLoadOp::CLEAR,
+                final_layout: ImageLayout::PRESENT_SRC_KHR,
+                ..Default::default()
+            }],
+            subpasses: vec![SubPass::new()],
+        })
+        .unwrap();
 
-    for i in image_view {
-        frame_buffers.push(
-            i.create_frame_buffer(&device, &render_pass, 640, 480)
-                .unwrap(),
-        );
-    }
+    let mut frame_buffers = create_frame_buffers(&device, &render_pass, &swapchain);
 
     let fragment_shader = device
         .create_shader_module(
-            Spirv::new("examples/shader/shader.frag.spv"),
+            Spirv::new("examples/shader/shader.frag.spv").unwrap(),
             ShaderKind::Fragment,
         )
         .unwrap();
     let vertex_shader = device
         .create_shader_module(
-            Spirv::new("examples/shader/shader.vert.spv"),
+            Spirv::new("examples/shader/shader.vert.spv").unwrap(),
             ShaderKind::Vertex,
         )
         .unwrap();
     let pipeline = render_pass
-        .create_pipeline(&image, &device, &[fragment_shader, vertex_shader])
+        .create_graphics_pipeline(
+            &device,
+            &[fragment_shader, vertex_shader],
+            &GraphicsPipelineDesc::new(),
+        )
         .unwrap();
 
-    let fence = device.create_fence().unwrap();
+    let mut frames = device.create_frame_ring(&queue, 2).unwrap();
+    let mut needs_recreate = false;
 
     event_loop.run(move |event, _, control_flow| {
         control_flow.set_wait();
@@ -97,26 +90,97 @@ fn main() {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 window_id,
-            } if window_id == window.id() => control_flow.set_exit(),
+            } if window_id == window.id() => {
+                device.wait_idle().unwrap();
+                control_flow.set_exit();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Resized(_),
+                ..
+            } => needs_recreate = true,
             Event::MainEventsCleared => {
-                let acquire_image_index = device.acquire_next_image(&swapchain, &fence);
-                gallium.reset(&device);
-                gallium.begin_draw(&device);
+                let size = window.inner_size();
+                if size.width == 0 || size.height == 0 {
+                    // Minimized
+                    return;
+                }
+                if needs_recreate {
+                    swapchain
+                        .recreate(Extent2D {
+                            width: size.width,
+                            height: size.height,
+                        })
+                        .unwrap();
+                    frame_buffers = create_frame_buffers(&device, &render_pass, &swapchain);
+                    needs_recreate = false;
+                }
+
+                let frame = frames.next_frame().unwrap();
+                let acquire_image_index = match device
+                    .acquire_next_image(&swapchain, Some(frame.image_available()), None)
+                    .unwrap()
+                {
+                    AcquireResult::Ok(i) => i,
+                    AcquireResult::Suboptimal(i) => {
+                        needs_recreate = true;
+                        i
+                    }
+                    AcquireResult::OutOfDate => {
+                        needs_recreate = true;
+                        return;
+                    }
+                };
+                let extent = swapchain.extent();
+                let gallium = frame.gallium();
+                gallium.begin_draw(&device).unwrap();
                 gallium.begin_render_pass(
                     &device,
                     &frame_buffers[acquire_image_index],
                     &render_pass,
-                    640,
-                    480,
+                    extent.width,
+                    extent.height,
+                    &[ClearValue::Color([0.0, 0.0, 0.0, 1.0])],
                 );
-                gallium.end_render_pass(&device);
-                gallium.bind_pipeline(&device, &pipeline[0]);
+                gallium.bind_pipeline(&device, &pipeline);
                 gallium.draw(&device, 3, 1, 0, 0);
-                gallium.end_draw(&device);
-                queue.present(&swapchain, acquire_image_index);
-                device.dispatch_to_queue(&gallium, &queue);
+                gallium.end_render_pass(&device);
+                gallium.end_draw(&device).unwrap();
+                frame
+                    .submit(
+                        &device,
+                        &queue,
+                        SubmitDesc::new()
+                            .wait(
+                                frame.image_available(),
+                                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                            )
+                            .signal(frame.render_finished()),
+                    )
+                    .unwrap();
+                let result = queue
+                    .present(&swapchain, acquire_image_index, &[frame.render_finished()])
+                    .unwrap();
+                if result.needs_recreate() {
+                    needs_recreate = true;
+                }
             }
             _ => (),
         }
     });
 }
+
+fn create_frame_buffers(
+    device: &Device,
+    render_pass: &RenderPass,
+    swapchain: &Swapchain,
+) -> Vec<FrameBuffer> {
+    let extent = swapchain.extent();
+    swapchain
+        .image_views()
+        .iter()
+        .map(|view| {
+            view.create_frame_buffer(device, render_pass, extent.width, extent.height)
+                .unwrap()
+        })
+        .collect()
+}
@@ -0,0 +1,535 @@
+use std::ptr::NonNull;
+
+use ash::vk::{
+    DeviceMemory, MappedMemoryRange, MemoryAllocateInfo, MemoryMapFlags, MemoryPropertyFlags,
+    MemoryRequirements, PhysicalDeviceLimits, PhysicalDeviceMemoryProperties, WHOLE_SIZE,
+};
+
+use crate::{find_memory_type, Device, Error, ErrorKind, MemoryLocation, RawDevice, Result};
+
+/// Size of the blocks of device-local memory types.
+const DEVICE_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+/// Size of the blocks of the other memory types.
+const HOST_BLOCK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Statistics of the memory allocated by a device
+///
+/// # Value Meaning
+/// * `block_count` - Number of `VkDeviceMemory` objects, which count towards `maxMemoryAllocationCount`.
+/// * `allocation_count` - Number of buffers and images placed in the blocks.
+/// * `allocated_bytes` - Total size of the blocks.
+/// * `used_bytes` - Bytes of the blocks used by buffers and images, without alignment padding.
+///
+/// # Example
+/// ```no_run
+/// # fn example(device: &gallium::Device) {
+/// let stats = device.memory_stats();
+/// println!("{} bytes in {} blocks", stats.allocated_bytes, stats.block_count);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub block_count: u32,
+    pub allocation_count: u32,
+    pub allocated_bytes: u64,
+    pub used_bytes: u64,
+}
+
+/// A range of a memory block owned by a buffer or an image
+///
+/// It must be returned with `RawDevice::free` after the resource is destroyed.
+pub(crate) struct Allocation {
+    pub(crate) memory: DeviceMemory,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+    pub(crate) memory_flags: MemoryPropertyFlags,
+    mapped: Option<NonNull<u8>>,
+}
+
+// The pointer refers to memory that stays mapped while the allocation is alive.
+unsafe impl Send for Allocation {}
+// Shared references only read through the pointer; the resources owning an allocation
+// require `&mut self` to write to it.
+unsafe impl Sync for Allocation {}
+
+impl Allocation {
+    /// Pointer to the start of the allocation, or None if it is not host visible.
+    pub(crate) fn mapped_ptr(&self) -> Option<*mut u8> {
+        self.mapped.map(|p| p.as_ptr())
+    }
+
+    /// Range of the allocation to flush or invalidate, or None if the memory is coherent.
+    ///
+    /// Allocations in non-coherent memory are aligned to `nonCoherentAtomSize`,
+    /// so the range does not touch the neighbouring allocations.
+    pub(crate) fn non_coherent_range(&self) -> Option<MappedMemoryRange> {
+        if self
+            .memory_flags
+            .contains(MemoryPropertyFlags::HOST_COHERENT)
+        {
+            return None;
+        }
+        Some(
+            MappedMemoryRange::builder()
+                .memory(self.memory)
+                .offset(self.offset)
+                .size(self.size)
+                .build(),
+        )
+    }
+}
+
+/// A `VkDeviceMemory` that is split into allocations
+struct MemoryBlock {
+    memory: DeviceMemory,
+    size: u64,
+    memory_type_index: u32,
+    /// Whether the block holds buffers and linear images, or optimal images.
+    linear: bool,
+    /// Blocks made for a single large allocation are not shared.
+    dedicated: bool,
+    mapped: Option<NonNull<u8>>,
+    /// Free ranges as (offset, size), sorted by offset and never adjacent.
+    free: Vec<(u64, u64)>,
+    allocation_count: u32,
+    used: u64,
+}
+
+impl MemoryBlock {
+    /// Take `size` bytes aligned to `alignment` with the first fit, returning the offset.
+    fn take(&mut self, size: u64, alignment: u64) -> Option<u64> {
+        let i = self
+            .free
+            .iter()
+            .position(|&(start, len)| align_up(start, alignment) + size <= start + len)?;
+        let (start, len) = self.free.remove(i);
+        let offset = align_up(start, alignment);
+        let end = offset + size;
+        if end < start + len {
+            self.free.insert(i, (end, start + len - end));
+        }
+        if offset > start {
+            self.free.insert(i, (start, offset - start));
+        }
+        self.allocation_count += 1;
+        self.used += size;
+        Some(offset)
+    }
+
+    /// Give back a range returned by `take`, merging it with its free neighbours.
+    fn release(&mut self, offset: u64, size: u64) {
+        let i = self.free.partition_point(|&(start, _)| start < offset);
+        self.free.insert(i, (offset, size));
+        if i + 1 < self.free.len() && offset + size == self.free[i + 1].0 {
+            self.free[i].1 += self.free[i + 1].1;
+            self.free.remove(i + 1);
+        }
+        if i > 0 && self.free[i - 1].0 + self.free[i - 1].1 == offset {
+            self.free[i - 1].1 += self.free[i].1;
+            self.free.remove(i);
+        }
+        self.allocation_count -= 1;
+        self.used -= size;
+    }
+}
+
+/// Places buffers and images in large blocks of memory
+///
+/// The number of `VkDeviceMemory` objects is limited by `maxMemoryAllocationCount`, which can be
+/// as low as 4096, so resources share blocks instead of allocating their own memory.
+/// Buffers and linear images are kept apart from optimal images when the device has a
+/// `bufferImageGranularity` larger than 1, so that they never share a page.
+/// Empty blocks are freed.
+pub(crate) struct Allocator {
+    memory_properties: PhysicalDeviceMemoryProperties,
+    buffer_image_granularity: u64,
+    non_coherent_atom_size: u64,
+    blocks: Vec<MemoryBlock>,
+}
+
+// The block pointers refer to mapped memory owned by the allocator.
+unsafe impl Send for Allocator {}
+
+impl Allocator {
+    pub(crate) fn new(
+        memory_properties: PhysicalDeviceMemoryProperties,
+        limits: &PhysicalDeviceLimits,
+    ) -> Self {
+        Self {
+            memory_properties,
+            buffer_image_granularity: limits.buffer_image_granularity.max(1),
+            non_coherent_atom_size: limits.non_coherent_atom_size.max(1),
+            blocks: vec![],
+        }
+    }
+
+    /// Size of new blocks of a memory type, kept small relative to its heap.
+    fn block_size(&self, memory_type_index: u32) -> u64 {
+        let memory_type = self.memory_properties.memory_types[memory_type_index as usize];
+        let heap_size = self.memory_properties.memory_heaps[memory_type.heap_index as usize].size;
+        let size = if memory_type
+            .property_flags
+            .contains(MemoryPropertyFlags::DEVICE_LOCAL)
+        {
+            DEVICE_BLOCK_SIZE
+        } else {
+            HOST_BLOCK_SIZE
+        };
+        size.min(heap_size / 8).max(1)
+    }
+
+    /// Allocate memory for a resource
+    ///
+    /// # Arguments
+    ///
+    /// * `linear` - True for buffers and linear images, false for optimal images.
+    pub(crate) fn allocate(
+        &mut self,
+        device: &ash::Device,
+        requirements: &MemoryRequirements,
+        location: MemoryLocation,
+        linear: bool,
+        operation: &'static str,
+    ) -> Result<Allocation> {
+        let memory_type_index = find_memory_type(
+            &self.memory_properties,
+            requirements.memory_type_bits,
+            location.required_flags(),
+            location.preferred_flags(),
+        )
+        .ok_or_else(|| {
+            Error::new(ErrorKind::OutOfMemory, operation)
+                .with_context(format!("no memory type for {:?}", location))
+        })?;
+        let memory_flags =
+            self.memory_properties.memory_types[memory_type_index as usize].property_flags;
+
+        let mut alignment = requirements.alignment.max(1);
+        let mut size = requirements.size;
+        if memory_flags.contains(MemoryPropertyFlags::HOST_VISIBLE)
+            && !memory_flags.contains(MemoryPropertyFlags::HOST_COHERENT)
+        {
+            alignment = alignment.max(self.non_coherent_atom_size);
+            size = align_up(size, self.non_coherent_atom_size);
+        }
+
+        if let Some((i, offset)) = self.take_from_blocks(memory_type_index, linear, size, alignment)
+        {
+            return Ok(allocation(&self.blocks[i], offset, size, memory_flags));
+        }
+
+        let block_size = self.block_size(memory_type_index);
+        let dedicated = size > block_size / 2;
+        let mut block = new_block(
+            device,
+            memory_type_index,
+            if dedicated { size } else { block_size },
+            linear,
+            dedicated,
+            memory_flags,
+        )?;
+        let offset = block
+            .take(size, alignment)
+            .expect("a new block fits the allocation");
+        let allocation = allocation(&block, offset, size, memory_flags);
+        self.blocks.push(block);
+        Ok(allocation)
+    }
+
+    /// Take a range from an existing block that may hold the resource, returning the index of
+    /// the block and the offset.
+    fn take_from_blocks(
+        &mut self,
+        memory_type_index: u32,
+        linear: bool,
+        size: u64,
+        alignment: u64,
+    ) -> Option<(usize, u64)> {
+        let granularity = self.buffer_image_granularity;
+        self.blocks.iter_mut().enumerate().find_map(|(i, block)| {
+            let compatible = block.memory_type_index == memory_type_index
+                && !block.dedicated
+                && (granularity == 1 || block.linear == linear);
+            if !compatible {
+                return None;
+            }
+            block.take(size, alignment).map(|offset| (i, offset))
+        })
+    }
+
+    /// Return an allocation to its block, freeing the block if it becomes empty.
+    pub(crate) fn free(&mut self, device: &ash::Device, allocation: &Allocation) {
+        let Some(i) = self
+            .blocks
+            .iter()
+            .position(|b| b.memory == allocation.memory)
+        else {
+            log::error!("freeing memory that was not allocated by the allocator");
+            return;
+        };
+        let block = &mut self.blocks[i];
+        block.release(allocation.offset, allocation.size);
+        if block.allocation_count == 0 {
+            let block = self.blocks.swap_remove(i);
+            // Freeing memory also unmaps it.
+            unsafe { device.free_memory(block.memory, None) };
+        }
+    }
+
+    pub(crate) fn stats(&self) -> MemoryStats {
+        self.blocks
+            .iter()
+            .fold(MemoryStats::default(), |stats, block| MemoryStats {
+                block_count: stats.block_count + 1,
+                allocation_count: stats.allocation_count + block.allocation_count,
+                allocated_bytes: stats.allocated_bytes + block.size,
+                used_bytes: stats.used_bytes + block.used,
+            })
+    }
+
+    /// Free all blocks before the device is destroyed.
+    pub(crate) fn destroy(&mut self, device: &ash::Device) {
+        for block in self.blocks.drain(..) {
+            unsafe { device.free_memory(block.memory, None) };
+        }
+    }
+}
+
+fn new_block(
+    device: &ash::Device,
+    memory_type_index: u32,
+    size: u64,
+    linear: bool,
+    dedicated: bool,
+    memory_flags: MemoryPropertyFlags,
+) -> Result<MemoryBlock> {
+    let allocate_info = MemoryAllocateInfo::builder()
+        .allocation_size(size)
+        .memory_type_index(memory_type_index)
+        .build();
+    let memory = unsafe { device.allocate_memory(&allocate_info, None) }
+        .map_err(|e| Error::from_vk(e, "vkAllocateMemory"))?;
+    // Host-visible blocks stay mapped, since a memory object can only be mapped once at a time.
+    let mapped = if memory_flags.contains(MemoryPropertyFlags::HOST_VISIBLE) {
+        match unsafe { device.map_memory(memory, 0, WHOLE_SIZE, MemoryMapFlags::empty()) } {
+            Ok(ptr) => NonNull::new(ptr as *mut u8),
+            Err(e) => {
+                unsafe { device.free_memory(memory, None) };
+                return Err(Error::from_vk(e, "vkMapMemory"));
+            }
+        }
+    } else {
+        None
+    };
+    Ok(MemoryBlock {
+        memory,
+        size,
+        memory_type_index,
+        linear,
+        dedicated,
+        mapped,
+        free: vec![(0, size)],
+        allocation_count: 0,
+        used: 0,
+    })
+}
+
+fn allocation(
+    block: &MemoryBlock,
+    offset: u64,
+    size: u64,
+    memory_flags: MemoryPropertyFlags,
+) -> Allocation {
+    Allocation {
+        memory: block.memory,
+        offset,
+        size,
+        memory_flags,
+        mapped: block
+            .mapped
+            .map(|p| unsafe { NonNull::new_unchecked(p.as_ptr().add(offset as usize)) }),
+    }
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
+
+impl RawDevice {
+    /// Allocate memory for a resource
+    ///
+    /// # Arguments
+    ///
+    /// * `linear` - True for buffers and linear images, false for optimal images.
+    pub(crate) fn allocate(
+        &self,
+        requirements: &MemoryRequirements,
+        location: MemoryLocation,
+        linear: bool,
+        operation: &'static str,
+    ) -> Result<Allocation> {
+        self.allocator
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .allocate(&self.inner, requirements, location, linear, operation)
+    }
+
+    /// Free memory returned by `allocate` after the resource using it has been destroyed.
+    pub(crate) fn free(&self, allocation: &Allocation) {
+        self.allocator
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .free(&self.inner, allocation);
+    }
+}
+
+impl Device {
+    /// Statistics of the memory allocated for buffers and images.
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.inner
+            .allocator
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(size: u64, linear: bool) -> MemoryBlock {
+        MemoryBlock {
+            memory: DeviceMemory::null(),
+            size,
+            memory_type_index: 0,
+            linear,
+            dedicated: false,
+            mapped: None,
+            free: vec![(0, size)],
+            allocation_count: 0,
+            used: 0,
+        }
+    }
+
+    fn allocator(buffer_image_granularity: u64) -> Allocator {
+        Allocator::new(
+            PhysicalDeviceMemoryProperties::default(),
+            &PhysicalDeviceLimits {
+                buffer_image_granularity,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn aligned_take_leaves_padding() {
+        let mut block = block(1024, true);
+        assert_eq!(block.take(10, 1), Some(0));
+        assert_eq!(block.take(100, 256), Some(256));
+        assert_eq!(block.free, vec![(10, 246), (356, 668)]);
+        // The padding hole is used by later allocations that fit.
+        assert_eq!(block.take(200, 8), Some(16));
+        assert_eq!(block.free, vec![(10, 6), (216, 40), (356, 668)]);
+        assert_eq!(block.allocation_count, 3);
+        assert_eq!(block.used, 310);
+    }
+
+    #[test]
+    fn take_fails_when_nothing_fits() {
+        let mut block = block(1024, true);
+        assert_eq!(block.take(512, 1), Some(0));
+        assert_eq!(block.take(100, 1024), None);
+        assert_eq!(block.take(513, 1), None);
+        assert_eq!(block.allocation_count, 1);
+    }
+
+    #[test]
+    fn release_merges_left() {
+        let mut block = block(1024, true);
+        let a = block.take(100, 1).unwrap();
+        let b = block.take(100, 1).unwrap();
+        block.take(100, 1).unwrap();
+        block.release(a, 100);
+        assert_eq!(block.free, vec![(0, 100), (300, 724)]);
+        block.release(b, 100);
+        assert_eq!(block.free, vec![(0, 200), (300, 724)]);
+    }
+
+    #[test]
+    fn release_merges_right() {
+        let mut block = block(1024, true);
+        block.take(100, 1).unwrap();
+        let b = block.take(100, 1).unwrap();
+        let c = block.take(100, 1).unwrap();
+        block.release(c, 100);
+        assert_eq!(block.free, vec![(200, 824)]);
+        block.release(b, 100);
+        assert_eq!(block.free, vec![(100, 924)]);
+    }
+
+    #[test]
+    fn release_merges_both_ways() {
+        let mut block = block(1024, true);
+        let a = block.take(100, 1).unwrap();
+        let b = block.take(100, 1).unwrap();
+        let c = block.take(100, 1).unwrap();
+        block.take(100, 1).unwrap();
+        block.release(a, 100);
+        block.release(c, 100);
+        assert_eq!(block.free, vec![(0, 100), (200, 100), (400, 624)]);
+        block.release(b, 100);
+        assert_eq!(block.free, vec![(0, 300), (400, 624)]);
+        assert_eq!(block.allocation_count, 1);
+        assert_eq!(block.used, 100);
+    }
+
+    #[test]
+    fn full_block_becomes_empty() {
+        let mut block = block(1024, true);
+        let a = block.take(512, 1).unwrap();
+        let b = block.take(512, 1).unwrap();
+        assert!(block.free.is_empty());
+        assert_eq!(block.take(1, 1), None);
+        block.release(b, 512);
+        block.release(a, 512);
+        assert_eq!(block.free, vec![(0, 1024)]);
+        assert_eq!(block.allocation_count, 0);
+        assert_eq!(block.used, 0);
+    }
+
+    #[test]
+    fn linear_and_optimal_are_kept_apart_with_granularity() {
+        let mut allocator = allocator(1024);
+        allocator.blocks.push(block(4096, true));
+        assert_eq!(allocator.take_from_blocks(0, false, 256, 1), None);
+        assert_eq!(allocator.take_from_blocks(0, true, 256, 1), Some((0, 0)));
+
+        allocator.blocks.push(block(4096, false));
+        assert_eq!(allocator.take_from_blocks(0, false, 256, 1), Some((1, 0)));
+        assert_eq!(allocator.take_from_blocks(0, true, 256, 1), Some((0, 256)));
+    }
+
+    #[test]
+    fn linear_and_optimal_share_without_granularity() {
+        let mut allocator = allocator(1);
+        allocator.blocks.push(block(4096, true));
+        assert_eq!(allocator.take_from_blocks(0, false, 256, 1), Some((0, 0)));
+        assert_eq!(allocator.take_from_blocks(0, true, 256, 1), Some((0, 256)));
+    }
+
+    #[test]
+    fn other_memory_types_and_dedicated_blocks_are_not_shared() {
+        let mut allocator = allocator(1);
+        allocator.blocks.push(MemoryBlock {
+            dedicated: true,
+            ..block(4096, true)
+        });
+        allocator.blocks.push(MemoryBlock {
+            memory_type_index: 1,
+            ..block(4096, true)
+        });
+        assert_eq!(allocator.take_from_blocks(0, true, 256, 1), None);
+        assert_eq!(allocator.take_from_blocks(1, true, 256, 1), Some((1, 0)));
+    }
+}
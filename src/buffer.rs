@@ -0,0 +1,314 @@
+use std::sync::Arc;
+
+pub use ash::vk::{BufferUsageFlags, VertexInputRate};
+
+use ash::vk::{
+    BufferCreateInfo, Format, SharingMode, VertexInputAttributeDescription,
+    VertexInputBindingDescription,
+};
+use bytemuck::Pod;
+
+use crate::{
+    Allocation, Device, Error, ErrorKind, MappedMemory, MemoryLocation, RawDevice, Result,
+};
+
+/// Description for Buffer Creation
+///
+/// # Example
+/// ```no_run
+/// use gallium::{BufferDesc, BufferUsageFlags, MemoryLocation};
+/// # fn example(device: &gallium::Device) {
+///
+/// let vertices: [[f32; 2]; 3] = [[0.0, -0.5], [0.5, 0.5], [-0.5, 0.5]];
+/// let mut buffer = device
+///     .create_buffer(&BufferDesc {
+///         size: std::mem::size_of_val(&vertices) as u64,
+///         usage: BufferUsageFlags::VERTEX_BUFFER,
+///         location: MemoryLocation::CpuToGpu,
+///     })
+///     .unwrap();
+/// buffer.write(0, &vertices).unwrap();
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct BufferDesc {
+    /// Size in bytes.
+    pub size: u64,
+    pub usage: BufferUsageFlags,
+    pub location: MemoryLocation,
+}
+
+/// Represents a buffer and its memory
+///
+/// It can be created with create_buffer from Device
+pub struct Buffer {
+    pub(crate) inner: ash::vk::Buffer,
+    pub(crate) allocation: Allocation,
+    pub(crate) size: u64,
+    pub(crate) usage: BufferUsageFlags,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_buffer(self.inner, None);
+        }
+        self.device.free(&self.allocation);
+    }
+}
+
+impl Buffer {
+    /// Size of the buffer in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn usage(&self) -> BufferUsageFlags {
+        self.usage
+    }
+
+    /// Returns true if the CPU can read and write the buffer.
+    pub fn is_host_visible(&self) -> bool {
+        self.allocation.mapped_ptr().is_some()
+    }
+
+    fn check_range(&self, offset: u64, len: usize, operation: &'static str) -> Result<()> {
+        if !self.is_host_visible() {
+            return Err(Error::new(ErrorKind::InvalidValue, operation)
+                .with_context("the buffer is not host visible"));
+        }
+        match offset.checked_add(len as u64) {
+            Some(end) if end <= self.size => Ok(()),
+            _ => Err(
+                Error::new(ErrorKind::InvalidValue, operation).with_context(format!(
+                    "range {}..{} exceeds the buffer size {}",
+                    offset,
+                    offset.saturating_add(len as u64),
+                    self.size
+                )),
+            ),
+        }
+    }
+
+    /// Map the memory of the buffer for CPU access
+    ///
+    /// The buffer must be host visible, see [Buffer::is_host_visible].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn example(buffer: &mut gallium::Buffer) {
+    /// let mut memory = buffer.map_memory().unwrap();
+    /// memory.as_slice_mut::<f32>().unwrap().fill(0.0);
+    /// # }
+    /// ```
+    pub fn map_memory(&mut self) -> Result<MappedMemory<'_>> {
+        MappedMemory::new(
+            &self.device,
+            &self.allocation,
+            self.size,
+            "Buffer::map_memory",
+        )
+    }
+
+    /// Copy `data` into the buffer from the CPU.
+    ///
+    /// The buffer must be created with `MemoryLocation::CpuToGpu` or `MemoryLocation::GpuToCpu`.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Offset in bytes from the start of the buffer.
+    /// * `data` - Data to be written.
+    pub fn write<T: Pod>(&mut self, offset: u64, data: &[T]) -> Result<()> {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        self.check_range(offset, bytes.len(), "Buffer::write")?;
+        let ptr = self
+            .allocation
+            .mapped_ptr()
+            .expect("checked by check_range");
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.add(offset as usize), bytes.len());
+        }
+        match self.allocation.non_coherent_range() {
+            Some(range) => unsafe { self.device.flush_mapped_memory_ranges(&[range]) }
+                .map_err(|e| Error::from_vk(e, "vkFlushMappedMemoryRanges")),
+            None => Ok(()),
+        }
+    }
+
+    /// Copy the contents of the buffer into `data`.
+    ///
+    /// The buffer must be host visible, and the GPU must have finished writing to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Offset in bytes from the start of the buffer.
+    /// * `data` - Receives the contents.
+    pub fn read<T: Pod>(&mut self, offset: u64, data: &mut [T]) -> Result<()> {
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(data);
+        self.check_range(offset, bytes.len(), "Buffer::read")?;
+        let ptr = self
+            .allocation
+            .mapped_ptr()
+            .expect("checked by check_range");
+        if let Some(range) = self.allocation.non_coherent_range() {
+            unsafe { self.device.invalidate_mapped_memory_ranges(&[range]) }
+                .map_err(|e| Error::from_vk(e, "vkInvalidateMappedMemoryRanges"))?;
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                ptr.add(offset as usize),
+                bytes.as_mut_ptr(),
+                bytes.len(),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Device {
+    /// Create a buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `desc` - Size, usage and memory location of the buffer. The size must not be 0.
+    pub fn create_buffer(&self, desc: &BufferDesc) -> Result<Buffer> {
+        if desc.size == 0 {
+            return Err(
+                Error::new(ErrorKind::InvalidValue, "Device::create_buffer").with_context("size 0")
+            );
+        }
+        let create_info = BufferCreateInfo::builder()
+            .size(desc.size)
+            .usage(desc.usage)
+            .sharing_mode(SharingMode::EXCLUSIVE)
+            .build();
+        let inner = unsafe { self.inner.create_buffer(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateBuffer"))?;
+
+        let requirements = unsafe { self.inner.get_buffer_memory_requirements(inner) };
+        let allocation =
+            match self
+                .inner
+                .allocate(&requirements, desc.location, true, "Device::create_buffer")
+            {
+                Ok(a) => a,
+                Err(e) => {
+                    unsafe { self.inner.destroy_buffer(inner, None) };
+                    return Err(e);
+                }
+            };
+        if let Err(e) = unsafe {
+            self.inner
+                .bind_buffer_memory(inner, allocation.memory, allocation.offset)
+        } {
+            unsafe { self.inner.destroy_buffer(inner, None) };
+            self.inner.free(&allocation);
+            return Err(Error::from_vk(e, "vkBindBufferMemory"));
+        }
+
+        Ok(Buffer {
+            inner,
+            allocation,
+            size: desc.size,
+            usage: desc.usage,
+            device: self.inner.clone(),
+        })
+    }
+}
+
+/// An attribute of a vertex, read by the vertex shader at `location`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub format: Format,
+    /// Offset in bytes from the start of the vertex.
+    pub offset: u32,
+}
+
+/// Layout of the vertices in one vertex buffer binding
+///
+/// Pass one layout per binding to [GraphicsPipelineDesc::vertex_layouts](crate::GraphicsPipelineDesc::vertex_layouts).
+///
+/// # Example
+/// ```
+/// use gallium::{Format, VertexLayout};
+///
+/// #[repr(C)]
+/// struct Vertex {
+///     position: [f32; 2],
+///     color: [f32; 3],
+/// }
+///
+/// let layout = VertexLayout::new(std::mem::size_of::<Vertex>() as u32)
+///     .attribute(0, Format::R32G32_SFLOAT, 0)
+///     .attribute(1, Format::R32G32B32_SFLOAT, 8);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VertexLayout {
+    pub stride: u32,
+    pub input_rate: VertexInputRate,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    /// Layout advanced once per vertex.
+    ///
+    /// * `stride` - Size of one vertex in bytes.
+    pub fn new(stride: u32) -> Self {
+        Self {
+            stride,
+            input_rate: VertexInputRate::VERTEX,
+            attributes: vec![],
+        }
+    }
+
+    /// Layout advanced once per instance.
+    ///
+    /// * `stride` - Size of the data of one instance in bytes.
+    pub fn per_instance(stride: u32) -> Self {
+        Self {
+            input_rate: VertexInputRate::INSTANCE,
+            ..Self::new(stride)
+        }
+    }
+
+    pub fn attribute(mut self, location: u32, format: Format, offset: u32) -> Self {
+        self.attributes.push(VertexAttribute {
+            location,
+            format,
+            offset,
+        });
+        self
+    }
+
+    pub(crate) fn to_vk(
+        layouts: &[VertexLayout],
+    ) -> (
+        Vec<VertexInputBindingDescription>,
+        Vec<VertexInputAttributeDescription>,
+    ) {
+        let mut bindings = vec![];
+        let mut attributes = vec![];
+        for (binding, layout) in layouts.iter().enumerate() {
+            bindings.push(
+                VertexInputBindingDescription::builder()
+                    .binding(binding as u32)
+                    .stride(layout.stride)
+                    .input_rate(layout.input_rate)
+                    .build(),
+            );
+            for attribute in &layout.attributes {
+                attributes.push(
+                    VertexInputAttributeDescription::builder()
+                        .binding(binding as u32)
+                        .location(attribute.location)
+                        .format(attribute.format)
+                        .offset(attribute.offset)
+                        .build(),
+                );
+            }
+        }
+        (bindings, attributes)
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    borrow::Cow,
+    ffi::{c_void, CStr},
+    fmt,
+};
+
+use ash::vk::{
+    Bool32, DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT,
+    DebugUtilsMessengerCallbackDataEXT, DebugUtilsMessengerCreateInfoEXT, DebugUtilsMessengerEXT,
+    FALSE,
+};
+
+pub use ash::vk::DebugUtilsMessageSeverityFlagsEXT as DebugSeverity;
+pub use ash::vk::DebugUtilsMessageTypeFlagsEXT as DebugMessageType;
+
+pub(crate) const VALIDATION_LAYER_NAME: &CStr = c"VK_LAYER_KHRONOS_validation";
+
+/// Callback that receives messages from the validation layer
+pub type DebugCallback = Box<dyn Fn(&DebugMessage) + Send + Sync>;
+
+/// A message reported by the validation layer or the driver
+///
+/// It is passed to [DebugCallback] or written to the `log` crate.
+#[derive(Debug)]
+pub struct DebugMessage<'a> {
+    pub severity: DebugSeverity,
+    pub message_type: DebugMessageType,
+    pub id_name: Cow<'a, str>,
+    pub id_number: i32,
+    pub message: Cow<'a, str>,
+}
+
+/// Description for the validation layer and debug messenger
+///
+/// Set it to `InstanceDesc::validation` to enable `VK_LAYER_KHRONOS_validation`.
+/// If no callback is given, messages are written to the `log` crate.
+///
+/// # Example
+/// ```
+/// use gallium::{Instance, InstanceDesc, ValidationDesc};
+///
+/// fn main() {
+///     let instance = Instance::new(InstanceDesc {
+///         app_name: "Example".to_owned(),
+///         validation: Some(ValidationDesc::default()),
+///     }).unwrap();
+///     if !instance.is_validation_enabled() {
+///         println!("VK_LAYER_KHRONOS_validation is not installed");
+///     }
+/// }
+/// ```
+pub struct ValidationDesc {
+    /// Severities of the messages to be reported.
+    pub severity: DebugSeverity,
+    /// Types of the messages to be reported.
+    pub message_type: DebugMessageType,
+    /// Receives the messages. `None` writes them to the `log` crate.
+    pub callback: Option<DebugCallback>,
+}
+
+impl Default for ValidationDesc {
+    fn default() -> Self {
+        Self {
+            severity: DebugSeverity::WARNING | DebugSeverity::ERROR,
+            message_type: DebugMessageType::GENERAL
+                | DebugMessageType::VALIDATION
+                | DebugMessageType::PERFORMANCE,
+            callback: None,
+        }
+    }
+}
+
+impl fmt::Debug for ValidationDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidationDesc")
+            .field("severity", &self.severity)
+            .field("message_type", &self.message_type)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+/// Owns the debug-utils messenger of an instance
+pub(crate) struct DebugMessenger {
+    pub(crate) loader: ash::extensions::ext::DebugUtils,
+    pub(crate) messenger: DebugUtilsMessengerEXT,
+}
+
+impl DebugMessenger {
+    pub(crate) fn create_info(
+        desc: &ValidationDesc,
+        callback: &Option<DebugCallback>,
+    ) -> DebugUtilsMessengerCreateInfoEXT {
+        DebugUtilsMessengerCreateInfoEXT::builder()
+            .message_severity(desc.severity)
+            .message_type(desc.message_type)
+            .pfn_user_callback(Some(debug_callback))
+            .user_data(callback as *const Option<DebugCallback> as *mut c_void)
+            .build()
+    }
+
+    pub(crate) fn destroy(&self) {
+        unsafe {
+            self.loader
+                .destroy_debug_utils_messenger(self.messenger, None);
+        }
+    }
+}
+
+fn log_message(message: &DebugMessage) {
+    let level = if message.severity.contains(DebugSeverity::ERROR) {
+        log::Level::Error
+    } else if message.severity.contains(DebugSeverity::WARNING) {
+        log::Level::Warn
+    } else if message.severity.contains(DebugSeverity::INFO) {
+        log::Level::Info
+    } else {
+        log::Level::Trace
+    };
+    log::log!(
+        target: "gallium::validation",
+        level,
+        "[{:?}] {} ({}): {}",
+        message.message_type,
+        message.id_name,
+        message.id_number,
+        message.message
+    );
+}
+
+unsafe extern "system" fn debug_callback(
+    severity: DebugUtilsMessageSeverityFlagsEXT,
+    message_type: DebugUtilsMessageTypeFlagsEXT,
+    data: *const DebugUtilsMessengerCallbackDataEXT,
+    user_data: *mut c_void,
+) -> Bool32 {
+    if data.is_null() {
+        return FALSE;
+    }
+    let data = &*data;
+    let id_name = if data.p_message_id_name.is_null() {
+        Cow::Borrowed("")
+    } else {
+        CStr::from_ptr(data.p_message_id_name).to_string_lossy()
+    };
+    let message = if data.p_message.is_null() {
+        Cow::Borrowed("")
+    } else {
+        CStr::from_ptr(data.p_message).to_string_lossy()
+    };
+    let message = DebugMessage {
+        severity,
+        message_type,
+        id_name,
+        id_number: data.message_id_number,
+        message,
+    };
+
+    let callback = (user_data as *const Option<DebugCallback>).as_ref();
+    match callback {
+        Some(Some(callback)) => callback(&message),
+        _ => log_message(&message),
+    }
+    FALSE
+}
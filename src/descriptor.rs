@@ -0,0 +1,477 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+pub use ash::vk::{DescriptorType, ShaderStageFlags};
+
+use ash::vk::{
+    DescriptorBufferInfo, DescriptorImageInfo, DescriptorPoolCreateFlags, DescriptorPoolCreateInfo,
+    DescriptorPoolSize, DescriptorSetAllocateInfo, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutCreateInfo, ImageLayout, WriteDescriptorSet,
+};
+
+use crate::{Buffer, Device, Error, ErrorKind, ImageView, RawDevice, Result, Sampler};
+
+/// A binding of a descriptor set layout
+///
+/// # Example
+/// ```no_run
+/// use gallium::{DescriptorBinding, ShaderStageFlags};
+/// # fn example(device: &gallium::Device) {
+///
+/// let layout = device
+///     .create_descriptor_set_layout(&[
+///         DescriptorBinding::uniform_buffer(0, ShaderStageFlags::VERTEX),
+///         DescriptorBinding::sampled_image(1, ShaderStageFlags::FRAGMENT),
+///         DescriptorBinding::sampler(2, ShaderStageFlags::FRAGMENT),
+///     ])
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DescriptorBinding {
+    pub binding: u32,
+    pub ty: DescriptorType,
+    /// Number of array elements of the binding.
+    pub count: u32,
+    /// Shader stages that can access the binding.
+    pub stages: ShaderStageFlags,
+}
+
+impl DescriptorBinding {
+    pub fn new(binding: u32, ty: DescriptorType, stages: ShaderStageFlags) -> Self {
+        Self {
+            binding,
+            ty,
+            count: 1,
+            stages,
+        }
+    }
+
+    pub fn uniform_buffer(binding: u32, stages: ShaderStageFlags) -> Self {
+        Self::new(binding, DescriptorType::UNIFORM_BUFFER, stages)
+    }
+
+    pub fn storage_buffer(binding: u32, stages: ShaderStageFlags) -> Self {
+        Self::new(binding, DescriptorType::STORAGE_BUFFER, stages)
+    }
+
+    pub fn sampled_image(binding: u32, stages: ShaderStageFlags) -> Self {
+        Self::new(binding, DescriptorType::SAMPLED_IMAGE, stages)
+    }
+
+    pub fn sampler(binding: u32, stages: ShaderStageFlags) -> Self {
+        Self::new(binding, DescriptorType::SAMPLER, stages)
+    }
+
+    pub fn combined_image_sampler(binding: u32, stages: ShaderStageFlags) -> Self {
+        Self::new(binding, DescriptorType::COMBINED_IMAGE_SAMPLER, stages)
+    }
+
+    /// Make the binding an array of `count` descriptors.
+    pub fn array(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+}
+
+/// Layout of a descriptor set
+///
+/// It can be created with create_descriptor_set_layout from Device.
+/// Cloning it is cheap; the layout is destroyed when the last clone is dropped.
+#[derive(Clone)]
+pub struct DescriptorSetLayout {
+    pub(crate) inner: Arc<RawDescriptorSetLayout>,
+}
+
+pub(crate) struct RawDescriptorSetLayout {
+    pub(crate) inner: ash::vk::DescriptorSetLayout,
+    pub(crate) bindings: Vec<DescriptorBinding>,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for RawDescriptorSetLayout {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_descriptor_set_layout(self.inner, None);
+        }
+    }
+}
+
+impl fmt::Debug for DescriptorSetLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DescriptorSetLayout")
+            .field("bindings", &self.inner.bindings)
+            .finish()
+    }
+}
+
+impl DescriptorSetLayout {
+    pub fn bindings(&self) -> &[DescriptorBinding] {
+        &self.inner.bindings
+    }
+}
+
+/// Description for Descriptor Pool Creation
+///
+/// A [DescriptorPool] creates Vulkan pools of this size whenever the current ones are full.
+///
+/// # Value Meaning
+/// * `max_sets` - Number of sets in each Vulkan pool.
+/// * `sizes` - Number of descriptors of each type in each Vulkan pool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DescriptorPoolDesc {
+    pub max_sets: u32,
+    pub sizes: Vec<(DescriptorType, u32)>,
+}
+
+impl Default for DescriptorPoolDesc {
+    fn default() -> Self {
+        Self {
+            max_sets: 64,
+            sizes: vec![
+                (DescriptorType::UNIFORM_BUFFER, 64),
+                (DescriptorType::STORAGE_BUFFER, 64),
+                (DescriptorType::SAMPLED_IMAGE, 64),
+                (DescriptorType::SAMPLER, 64),
+                (DescriptorType::COMBINED_IMAGE_SAMPLER, 64),
+                (DescriptorType::STORAGE_IMAGE, 16),
+            ],
+        }
+    }
+}
+
+pub(crate) struct RawDescriptorPool {
+    // vkAllocateDescriptorSets and vkFreeDescriptorSets need external synchronization
+    pub(crate) inner: Mutex<ash::vk::DescriptorPool>,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for RawDescriptorPool {
+    fn drop(&mut self) {
+        let pool = *self.inner.get_mut().unwrap_or_else(|e| e.into_inner());
+        unsafe {
+            self.device.destroy_descriptor_pool(pool, None);
+        }
+    }
+}
+
+/// Allocator of descriptor sets
+///
+/// It grows by creating a new Vulkan pool when the existing ones are exhausted.
+/// Each set is returned to its pool when it is dropped.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Buffer, DescriptorPoolDesc, DescriptorSetLayout, Device};
+/// # fn example(device: &Device, layout: &DescriptorSetLayout, uniform_buffer: &Buffer) {
+///
+/// let mut pool = device.create_descriptor_pool(&DescriptorPoolDesc::default()).unwrap();
+/// let mut set = pool.allocate(layout).unwrap();
+/// set.write_uniform_buffer(0, uniform_buffer, 0, uniform_buffer.size())
+///     .unwrap();
+/// # }
+/// ```
+pub struct DescriptorPool {
+    pub(crate) pools: Vec<Arc<RawDescriptorPool>>,
+    pub(crate) desc: DescriptorPoolDesc,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl DescriptorPool {
+    fn create_pool(&self) -> Result<Arc<RawDescriptorPool>> {
+        let pool_sizes: Vec<_> = self
+            .desc
+            .sizes
+            .iter()
+            .map(|(ty, count)| {
+                DescriptorPoolSize::builder()
+                    .ty(*ty)
+                    .descriptor_count(*count)
+                    .build()
+            })
+            .collect();
+        let create_info = DescriptorPoolCreateInfo::builder()
+            .flags(DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .max_sets(self.desc.max_sets)
+            .pool_sizes(&pool_sizes)
+            .build();
+        let inner = unsafe { self.device.create_descriptor_pool(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateDescriptorPool"))?;
+        Ok(Arc::new(RawDescriptorPool {
+            inner: Mutex::new(inner),
+            device: self.device.clone(),
+        }))
+    }
+
+    fn allocate_from(
+        pool: &Arc<RawDescriptorPool>,
+        layout: &DescriptorSetLayout,
+    ) -> std::result::Result<DescriptorSet, ash::vk::Result> {
+        let guard = pool.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let layouts = [layout.inner.inner];
+        let allocate_info = DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(*guard)
+            .set_layouts(&layouts)
+            .build();
+        let sets = unsafe { pool.device.allocate_descriptor_sets(&allocate_info) }?;
+        Ok(DescriptorSet {
+            inner: sets[0],
+            layout: layout.clone(),
+            pool: pool.clone(),
+        })
+    }
+
+    /// Allocate a descriptor set
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - Layout of the set.
+    pub fn allocate(&mut self, layout: &DescriptorSetLayout) -> Result<DescriptorSet> {
+        // Newer pools are the most likely to have room left.
+        for pool in self.pools.iter().rev() {
+            match Self::allocate_from(pool, layout) {
+                Ok(set) => return Ok(set),
+                Err(e) if ErrorKind::from(e) == ErrorKind::OutOfPoolMemory => continue,
+                Err(e) => return Err(Error::from_vk(e, "vkAllocateDescriptorSets")),
+            }
+        }
+        let pool = self.create_pool()?;
+        let set = Self::allocate_from(&pool, layout).map_err(|e| {
+            Error::from_vk(e, "vkAllocateDescriptorSets").with_context(
+                "the layout does not fit in an empty pool; enlarge DescriptorPoolDesc",
+            )
+        })?;
+        self.pools.push(pool);
+        Ok(set)
+    }
+}
+
+/// A set of descriptors allocated from a [DescriptorPool]
+///
+/// The resources written to the set must stay alive while the GPU uses the set.
+/// Writes return an error of kind `ErrorKind::InvalidValue` if the layout has no binding of the
+/// written type at that number.
+pub struct DescriptorSet {
+    pub(crate) inner: ash::vk::DescriptorSet,
+    pub(crate) layout: DescriptorSetLayout,
+    pub(crate) pool: Arc<RawDescriptorPool>,
+}
+
+impl Drop for DescriptorSet {
+    fn drop(&mut self) {
+        let guard = self.pool.inner.lock().unwrap_or_else(|e| e.into_inner());
+        unsafe {
+            let _ = self.pool.device.free_descriptor_sets(*guard, &[self.inner]);
+        }
+    }
+}
+
+impl DescriptorSet {
+    pub fn layout(&self) -> &DescriptorSetLayout {
+        &self.layout
+    }
+
+    /// Check that the layout has a `binding` of type `ty`.
+    fn check_binding(&self, binding: u32, ty: DescriptorType) -> Result<()> {
+        match self.layout.bindings().iter().find(|b| b.binding == binding) {
+            Some(b) if b.ty == ty => Ok(()),
+            Some(b) => Err(Error::new(ErrorKind::InvalidValue, "DescriptorSet::write")
+                .with_context(format!("binding {} is {:?}, not {:?}", binding, b.ty, ty))),
+            None => Err(Error::new(ErrorKind::InvalidValue, "DescriptorSet::write")
+                .with_context(format!("the layout has no binding {}", binding))),
+        }
+    }
+
+    fn write_buffer(
+        &mut self,
+        binding: u32,
+        ty: DescriptorType,
+        buffer: &Buffer,
+        offset: u64,
+        range: u64,
+    ) -> Result<()> {
+        self.check_binding(binding, ty)?;
+        let buffer_info = [DescriptorBufferInfo::builder()
+            .buffer(buffer.inner)
+            .offset(offset)
+            .range(range)
+            .build()];
+        let write = WriteDescriptorSet::builder()
+            .dst_set(self.inner)
+            .dst_binding(binding)
+            .descriptor_type(ty)
+            .buffer_info(&buffer_info)
+            .build();
+        unsafe {
+            self.pool.device.update_descriptor_sets(&[write], &[]);
+        }
+        Ok(())
+    }
+
+    fn write_image(
+        &mut self,
+        binding: u32,
+        ty: DescriptorType,
+        image_info: DescriptorImageInfo,
+    ) -> Result<()> {
+        self.check_binding(binding, ty)?;
+        let image_info = [image_info];
+        let write = WriteDescriptorSet::builder()
+            .dst_set(self.inner)
+            .dst_binding(binding)
+            .descriptor_type(ty)
+            .image_info(&image_info)
+            .build();
+        unsafe {
+            self.pool.device.update_descriptor_sets(&[write], &[]);
+        }
+        Ok(())
+    }
+
+    /// Write a uniform buffer to `binding`.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Offset in bytes of the uniform data in the buffer.
+    /// * `range` - Size in bytes of the uniform data.
+    pub fn write_uniform_buffer(
+        &mut self,
+        binding: u32,
+        buffer: &Buffer,
+        offset: u64,
+        range: u64,
+    ) -> Result<()> {
+        self.write_buffer(
+            binding,
+            DescriptorType::UNIFORM_BUFFER,
+            buffer,
+            offset,
+            range,
+        )
+    }
+
+    /// Write a storage buffer to `binding`.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Offset in bytes of the storage data in the buffer.
+    /// * `range` - Size in bytes of the storage data.
+    pub fn write_storage_buffer(
+        &mut self,
+        binding: u32,
+        buffer: &Buffer,
+        offset: u64,
+        range: u64,
+    ) -> Result<()> {
+        self.write_buffer(
+            binding,
+            DescriptorType::STORAGE_BUFFER,
+            buffer,
+            offset,
+            range,
+        )
+    }
+
+    /// Write a sampled image to `binding`.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - Layout of the image when the shader reads it, usually `SHADER_READ_ONLY_OPTIMAL`.
+    pub fn write_sampled_image(
+        &mut self,
+        binding: u32,
+        view: &ImageView,
+        layout: ImageLayout,
+    ) -> Result<()> {
+        let image_info = DescriptorImageInfo::builder()
+            .image_view(view.inner)
+            .image_layout(layout)
+            .build();
+        self.write_image(binding, DescriptorType::SAMPLED_IMAGE, image_info)
+    }
+
+    /// Write a storage image to `binding`. The image must be in the `GENERAL` layout.
+    pub fn write_storage_image(&mut self, binding: u32, view: &ImageView) -> Result<()> {
+        let image_info = DescriptorImageInfo::builder()
+            .image_view(view.inner)
+            .image_layout(ImageLayout::GENERAL)
+            .build();
+        self.write_image(binding, DescriptorType::STORAGE_IMAGE, image_info)
+    }
+
+    /// Write a sampler to `binding`.
+    pub fn write_sampler(&mut self, binding: u32, sampler: &Sampler) -> Result<()> {
+        let image_info = DescriptorImageInfo::builder()
+            .sampler(sampler.inner)
+            .build();
+        self.write_image(binding, DescriptorType::SAMPLER, image_info)
+    }
+
+    /// Write an image and a sampler to a `COMBINED_IMAGE_SAMPLER` `binding`.
+    pub fn write_combined_image_sampler(
+        &mut self,
+        binding: u32,
+        view: &ImageView,
+        layout: ImageLayout,
+        sampler: &Sampler,
+    ) -> Result<()> {
+        let image_info = DescriptorImageInfo::builder()
+            .image_view(view.inner)
+            .image_layout(layout)
+            .sampler(sampler.inner)
+            .build();
+        self.write_image(binding, DescriptorType::COMBINED_IMAGE_SAMPLER, image_info)
+    }
+}
+
+impl Device {
+    /// Create a descriptor set layout
+    ///
+    /// # Arguments
+    ///
+    /// * `bindings` - Bindings of the set. Each binding number must be unique.
+    pub fn create_descriptor_set_layout(
+        &self,
+        bindings: &[DescriptorBinding],
+    ) -> Result<DescriptorSetLayout> {
+        let vk_bindings: Vec<_> = bindings
+            .iter()
+            .map(|b| {
+                DescriptorSetLayoutBinding::builder()
+                    .binding(b.binding)
+                    .descriptor_type(b.ty)
+                    .descriptor_count(b.count)
+                    .stage_flags(b.stages)
+                    .build()
+            })
+            .collect();
+        let create_info = DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&vk_bindings)
+            .build();
+        let inner = unsafe { self.inner.create_descriptor_set_layout(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateDescriptorSetLayout"))?;
+        Ok(DescriptorSetLayout {
+            inner: Arc::new(RawDescriptorSetLayout {
+                inner,
+                bindings: bindings.to_vec(),
+                device: self.inner.clone(),
+            }),
+        })
+    }
+
+    /// Create a growable descriptor pool
+    ///
+    /// The first Vulkan pool is created right away.
+    pub fn create_descriptor_pool(&self, desc: &DescriptorPoolDesc) -> Result<DescriptorPool> {
+        let mut pool = DescriptorPool {
+            pools: vec![],
+            desc: desc.clone(),
+            device: self.inner.clone(),
+        };
+        let first = pool.create_pool()?;
+        pool.pools.push(first);
+        Ok(pool)
+    }
+}
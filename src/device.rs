@@ -1,28 +1,22 @@
-use std::io::Read;
-use std::os::raw::c_void;
 use std::{
-    ffi::{CStr, CString},
-    io::Cursor,
-    ptr::null_mut,
+    ffi::CStr,
+    ops::Deref,
+    sync::{Arc, Mutex},
 };
 
-use ash::vk::{CommandPoolCreateFlags, FenceCreateInfo, MemoryMapFlags};
-use ash::{
-    util::read_spv,
-    vk::{
-        AttachmentDescription, AttachmentLoadOp, AttachmentReference, AttachmentStoreOp,
-        CommandBuffer, CommandBufferAllocateInfo, CommandBufferBeginInfo, CommandBufferLevel,
-        CommandPool, CommandPoolCreateInfo, Extent2D, Extent3D, Format, ImageCreateInfo,
-        ImageLayout, ImageTiling, ImageType, ImageUsageFlags, MemoryAllocateInfo, Offset2D,
-        PhysicalDevice, PhysicalDeviceFeatures, PhysicalDeviceProperties, PipelineBindPoint,
-        QueueFamilyProperties, QueueFlags, Rect2D, RenderPassCreateInfo, SampleCountFlags,
-        ShaderModuleCreateInfo, SharingMode, SubmitInfo, SubpassDescription, Viewport,
-    },
+use ash::vk::{
+    AccessFlags, CommandBuffer, CommandBufferAllocateInfo, CommandBufferLevel,
+    CommandPoolCreateFlags, CommandPoolCreateInfo, Extent3D, FenceCreateFlags, FenceCreateInfo,
+    Format, FormatFeatureFlags, ImageCreateInfo, ImageLayout, ImageTiling, ImageUsageFlags,
+    PhysicalDevice, PhysicalDeviceFeatures, PhysicalDeviceProperties, PipelineStageFlags,
+    QueueFlags, RenderPassCreateInfo, ShaderModuleCreateInfo, SharingMode, SubpassDependency,
+    SUBPASS_EXTERNAL,
 };
 
 use crate::{
-    Fence, GMResult, GPUQueueInfo, Gallium, Image, Instance, Queue, RenderPass, Shader, ShaderKind,
-    Spirv, SubPass, Surface, Swapchain,
+    Allocator, Error, ErrorKind, Fence, GPUQueueInfo, Gallium, Image, ImageDesc, Instance,
+    MemoryLocation, Queue, RawInstance, RenderPass, RenderPassDesc, Result, Shader, ShaderKind,
+    Spirv, SubmitDesc,
 };
 
 /// Represents a physical device  
@@ -37,6 +31,7 @@ use crate::{
 /// fn main() {
 ///     let instance = match Instance::new(InstanceDesc {
 ///         app_name: "Triangle".to_owned(),
+///         ..Default::default()
 ///     }) {
 ///         Ok(i) => i,
 ///         Err(e) => panic!("{:?}",e),
@@ -58,7 +53,7 @@ pub struct GPU {
 }
 
 impl GPU {
-    pub fn is_support_graphics(&self, instance: &Instance, index: *mut GPUQueueInfo) -> bool {
+    pub fn is_support_graphics(&self, instance: &Instance, index: &mut GPUQueueInfo) -> bool {
         let queue_family_properties = unsafe {
             instance
                 .instance
@@ -66,21 +61,47 @@ impl GPU {
         };
         for (i, prop) in queue_family_properties.iter().enumerate() {
             if (prop.queue_flags & QueueFlags::GRAPHICS).as_raw() != 0 {
-                if !index.is_null() {
-                    unsafe {
-                        (*index).index = i as u32;
-                        (*index).count = prop.queue_count;
-                    }
-                }
+                index.index = i as u32;
+                index.count = prop.queue_count;
                 return true;
             }
         }
         false
     }
 
+    /// Find a queue family that supports compute
+    ///
+    /// A family dedicated to compute, without graphics, is preferred so that compute work
+    /// can run alongside rendering. Such a family has no graphics queue, so pass both this
+    /// and the graphics family to `Instance::create_device_with_queues`.
+    pub fn is_support_compute(&self, instance: &Instance, index: &mut GPUQueueInfo) -> bool {
+        let queue_family_properties = unsafe {
+            instance
+                .instance
+                .get_physical_device_queue_family_properties(self.device)
+        };
+        let dedicated = queue_family_properties.iter().position(|prop| {
+            prop.queue_flags.contains(QueueFlags::COMPUTE)
+                && !prop.queue_flags.contains(QueueFlags::GRAPHICS)
+        });
+        let any = || {
+            queue_family_properties
+                .iter()
+                .position(|prop| prop.queue_flags.contains(QueueFlags::COMPUTE))
+        };
+        match dedicated.or_else(any) {
+            Some(i) => {
+                index.index = i as u32;
+                index.count = queue_family_properties[i].queue_count;
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn name(&self) -> String {
         let cstr = unsafe { CStr::from_ptr(self.device_property.device_name.as_ptr()) };
-        cstr.to_str().unwrap().to_owned()
+        cstr.to_string_lossy().into_owned()
     }
 }
 
@@ -96,6 +117,7 @@ impl GPU {
 /// fn main() {
 ///     let instance = match Instance::new(InstanceDesc {
 ///         app_name: "Triangle".to_owned(),
+///         ..Default::default()
 ///     }) {
 ///         Ok(i) => i,
 ///         Err(e) => panic!("{:?}",e),
@@ -112,7 +134,45 @@ impl GPU {
 /// }
 /// ```
 pub struct Device {
+    pub(crate) inner: Arc<RawDevice>,
+}
+
+/// Owns the `VkDevice`
+///
+/// Objects created from the device hold an `Arc` of it so that they cannot outlive it.
+pub(crate) struct RawDevice {
     pub(crate) inner: ash::Device,
+    pub(crate) gpu: PhysicalDevice,
+    pub(crate) properties: PhysicalDeviceProperties,
+    /// Whether the `timelineSemaphore` feature was enabled.
+    pub(crate) timeline_semaphore: bool,
+    /// Features enabled on the device.
+    pub(crate) features: PhysicalDeviceFeatures,
+    /// Queue families the device was created with.
+    pub(crate) queue_families: Vec<u32>,
+    pub(crate) allocator: Mutex<Allocator>,
+    pub(crate) instance: Arc<RawInstance>,
+}
+
+impl Deref for RawDevice {
+    type Target = ash::Device;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl Drop for RawDevice {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.inner.device_wait_idle();
+            self.allocator
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .destroy(&self.inner);
+            self.inner.destroy_device(None);
+        }
+    }
 }
 
 impl Device {
@@ -120,294 +180,286 @@ impl Device {
     ///
     /// # Arguments
     ///
-    /// * `info` - GPU queue information to be acquired
-    pub fn get_queue(&self, info: GPUQueueInfo) -> Queue {
+    /// * `info` - GPU queue information to be acquired. Its family must be one the device was created with,
+    ///   otherwise an error of kind `ErrorKind::InvalidValue` is returned.
+    pub fn get_queue(&self, info: GPUQueueInfo) -> Result<Queue> {
+        if !self.inner.queue_families.contains(&info.index) {
+            return Err(
+                Error::new(ErrorKind::InvalidValue, "Device::get_queue").with_context(format!(
+                    "queue family {} was not created with the device",
+                    info.index
+                )),
+            );
+        }
         let inner = unsafe { self.inner.get_device_queue(info.index, 0) };
-        Queue { inner, info }
+        Ok(Queue { inner, info })
+    }
+
+    /// Wait until all queues of the device are idle.
+    ///
+    /// Call this before dropping resources that may still be used by the GPU.
+    pub fn wait_idle(&self) -> Result<()> {
+        unsafe { self.inner.device_wait_idle() }.map_err(|e| Error::from_vk(e, "vkDeviceWaitIdle"))
     }
 
-    pub fn create_gallium(&self, queue: &Queue) -> Result<Gallium, GMResult> {
+    /// Create a command recorder for `queue`
+    ///
+    /// A recorder must not be re-recorded while the GPU executes it; use a [FrameRing](crate::FrameRing)
+    /// to render several frames at once.
+    pub fn create_gallium(&self, queue: &Queue) -> Result<Gallium> {
         let create_info = CommandPoolCreateInfo::builder()
             .queue_family_index(queue.info.index)
             .flags(CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
             .build();
-        let command_pool = match unsafe { self.inner.create_command_pool(&create_info, None) } {
-            Ok(c) => c,
-            Err(e) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    _ => return Err(GMResult::UnknownError),
-                }
-            }
-        };
+        let command_pool = unsafe { self.inner.create_command_pool(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateCommandPool"))?;
         let allocate_info = CommandBufferAllocateInfo::builder()
             .command_pool(command_pool)
             .command_buffer_count(1)
             .level(CommandBufferLevel::PRIMARY)
             .build();
-        let command_buffers = match unsafe { self.inner.allocate_command_buffers(&allocate_info) } {
-            Ok(c) => c,
+        let command_buffer = match unsafe { self.inner.allocate_command_buffers(&allocate_info) } {
+            Ok(c) => c[0],
             Err(e) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    _ => return Err(GMResult::UnknownError),
-                }
+                unsafe { self.inner.destroy_command_pool(command_pool, None) };
+                return Err(Error::from_vk(e, "vkAllocateCommandBuffers"));
             }
         };
         Ok(Gallium {
             command_pool,
-            command_buffers,
+            command_buffer,
+            device: self.inner.clone(),
         })
     }
 
-    pub fn dispatch_to_queue(&self, gallium: &Gallium, queue: &Queue) {
-        let submit_info = SubmitInfo::builder()
-            .command_buffers(&gallium.command_buffers)
-            .build();
-        unsafe {
-            self.inner
-                .queue_submit(queue.inner, &[submit_info], ash::vk::Fence::null())
-                .unwrap()
-        };
+    /// Submit the commands recorded in `gallium` to `queue`.
+    ///
+    /// This does not wait for the GPU; use [Device::dispatch_to_queue_with_fence] to know
+    /// when the work has finished.
+    pub fn dispatch_to_queue(&self, gallium: &Gallium, queue: &Queue) -> Result<()> {
+        self.submit(queue, &SubmitDesc::new().gallium(gallium))
+    }
+
+    /// Submit the commands recorded in `gallium` to `queue` and signal `fence` when they finish.
+    ///
+    /// # Arguments
+    ///
+    /// * `fence` - An unsignaled fence.
+    pub fn dispatch_to_queue_with_fence(
+        &self,
+        gallium: &Gallium,
+        queue: &Queue,
+        fence: &Fence,
+    ) -> Result<()> {
+        self.submit(queue, &SubmitDesc::new().gallium(gallium).fence(fence))
+    }
+
+    /// Record commands with `record`, submit them to `queue` and wait until they have finished.
+    pub(crate) fn run_commands(
+        &self,
+        queue: &Queue,
+        record: impl FnOnce(CommandBuffer),
+    ) -> Result<()> {
+        let gallium = self.create_gallium(queue)?;
+        gallium.begin_draw(self)?;
+        record(gallium.command_buffer);
+        gallium.end_draw(self)?;
+        let fence = self.create_fence()?;
+        self.dispatch_to_queue_with_fence(&gallium, queue, &fence)?;
+        fence.wait(None)
     }
 
     /// Create an image
     ///
+    /// The image is a linear, host-visible `R8G8B8A8_UNORM` color attachment so that it can be
+    /// read with `Image::map_memory`. Use [Device::create_image_with_desc] for other images.
+    ///
     /// # Arguments
-    /// * `instance` -
+    /// * `instance` - Unused, memory is allocated by the device.
+    /// * `gpu` - Unused, memory is allocated by the device.
     pub fn create_image(
         &self,
-        instance: &Instance,
-        gpu: &GPU,
+        _instance: &Instance,
+        _gpu: &GPU,
         width: u32,
         height: u32,
-    ) -> Result<Image, GMResult> {
+    ) -> Result<Image> {
+        self.create_image_with_desc(&ImageDesc {
+            extent: Extent3D {
+                width,
+                height,
+                depth: 1,
+            },
+            tiling: ImageTiling::LINEAR,
+            usage: ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC,
+            location: MemoryLocation::GpuToCpu,
+            ..Default::default()
+        })
+    }
+
+    /// Create an image with the given description
+    ///
+    /// The combination of type, format, tiling and usage must be supported by the GPU,
+    /// otherwise a `FormatNotSupported` error is returned.
+    /// The image starts in the `UNDEFINED` layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `desc` - Type, format, size and usage of the image.
+    pub fn create_image_with_desc(&self, desc: &ImageDesc) -> Result<Image> {
+        desc.validate(&self.inner)?;
         let create_info = ImageCreateInfo::builder()
-            .image_type(ImageType::TYPE_2D)
-            .extent(
-                Extent3D::builder()
-                    .width(width)
-                    .height(height)
-                    .depth(1)
-                    .build(),
-            )
-            .mip_levels(1)
-            .array_layers(1)
-            .format(Format::R8G8B8A8_UNORM)
-            .tiling(ImageTiling::LINEAR)
+            .image_type(desc.image_type)
+            .extent(desc.extent)
+            .mip_levels(desc.mip_levels)
+            .array_layers(desc.array_layers)
+            .flags(desc.flags)
+            .format(desc.format)
+            .tiling(desc.tiling)
             .initial_layout(ImageLayout::UNDEFINED)
-            .usage(ImageUsageFlags::COLOR_ATTACHMENT)
+            .usage(desc.usage)
             .sharing_mode(SharingMode::EXCLUSIVE)
-            .samples(SampleCountFlags::TYPE_1)
+            .samples(desc.samples)
             .build();
-        let inner = match unsafe { self.inner.create_image(&create_info, None) } {
-            Ok(i) => i,
-            Err(e) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    _ => return Err(GMResult::UnknownError),
-                }
-            }
-        };
-
-        let mem_prop = unsafe {
-            instance
-                .instance
-                .get_physical_device_memory_properties(gpu.device)
-        };
-        let img_mem_required = unsafe { self.inner.get_image_memory_requirements(inner) };
-
-        let mut memory_type_index = 0;
-        let mut found_suitable_memory_type = false;
-
-        for i in 0..mem_prop.memory_type_count {
-            if (img_mem_required.memory_type_bits & (1 << i)) != 0 {
-                memory_type_index = i;
-                found_suitable_memory_type = true;
-            }
-        }
-
-        if found_suitable_memory_type == false {
-            panic!("Failed to allocate device memory for Image");
-        }
+        let inner = unsafe { self.inner.create_image(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateImage"))?;
 
-        let allocate_info = MemoryAllocateInfo::builder()
-            .allocation_size(img_mem_required.size)
-            .memory_type_index(memory_type_index)
-            .build();
-
-        let memory = match unsafe { self.inner.allocate_memory(&allocate_info, None) } {
-            Ok(m) => m,
+        let requirements = unsafe { self.inner.get_image_memory_requirements(inner) };
+        let allocation = match self.inner.allocate(
+            &requirements,
+            desc.location,
+            desc.tiling == ImageTiling::LINEAR,
+            "Device::create_image_with_desc",
+        ) {
+            Ok(a) => a,
             Err(e) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_INVALID_EXTERNAL_HANDLE => {
-                        return Err(GMResult::InvalidValue)
-                    }
-                    _ => return Err(GMResult::UnknownError),
-                }
+                unsafe { self.inner.destroy_image(inner, None) };
+                return Err(e);
             }
         };
-
-        match unsafe { self.inner.bind_image_memory(inner, memory, 0) } {
-            Ok(_) => {}
-            Err(e) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    _ => return Err(GMResult::UnknownError),
-                }
-            }
+        if let Err(e) = unsafe {
+            self.inner
+                .bind_image_memory(inner, allocation.memory, allocation.offset)
+        } {
+            unsafe { self.inner.destroy_image(inner, None) };
+            self.inner.free(&allocation);
+            return Err(Error::from_vk(e, "vkBindImageMemory"));
         }
 
-        let viewport = Viewport::builder()
-            .x(0.0)
-            .y(0.0)
-            .width(width as f32)
-            .height(height as f32)
-            .min_depth(0.0)
-            .max_depth(1.0)
-            .build();
-        let scissors = vec![Rect2D::builder()
-            .offset(Offset2D::builder().x(0.0 as i32).y(0.0 as i32).build())
-            .extent(Extent2D::builder().width(width).height(height).build())
-            .build()];
         Ok(Image {
-            viewport,
-            scissors,
-            memory,
-            img_mem_required,
+            allocation,
+            desc: *desc,
             inner,
+            device: self.inner.clone(),
         })
     }
 
-    pub fn create_render_pass(&self, subpasses: &[SubPass]) -> Result<RenderPass, GMResult> {
-        let attachment_descs = vec![AttachmentDescription::builder()
-            .format(Format::R8G8B8A8_UNORM)
-            .samples(SampleCountFlags::TYPE_1)
-            .load_op(AttachmentLoadOp::DONT_CARE)
-            .store_op(AttachmentStoreOp::STORE)
-            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
-            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
-            .initial_layout(ImageLayout::UNDEFINED)
-            .final_layout(ImageLayout::GENERAL)
-            .build()];
-
-        let mut subpass = vec![];
-        for i in subpasses {
-            subpass.push(i.0);
+    /// Check that OPTIMAL images of `format` support `features`.
+    pub(crate) fn check_format_features(
+        &self,
+        format: Format,
+        features: FormatFeatureFlags,
+        operation: &'static str,
+    ) -> Result<()> {
+        let supported = unsafe {
+            self.inner
+                .instance
+                .get_physical_device_format_properties(self.inner.gpu, format)
+        }
+        .optimal_tiling_features;
+        if supported.contains(features) {
+            Ok(())
+        } else {
+            Err(Error::new(ErrorKind::FormatNotSupported, operation)
+                .with_context(format!("{:?} does not support {:?}", format, features)))
         }
+    }
+
+    /// Create a render pass
+    ///
+    /// # Arguments
+    ///
+    /// * `desc` - Attachments and subpasses of the render pass.
+    pub fn create_render_pass(&self, desc: &RenderPassDesc) -> Result<RenderPass> {
+        let attachment_descs: Vec<_> = desc.attachments.iter().map(|a| a.to_vk()).collect();
+        let subpass: Vec<_> = desc.subpasses.iter().map(|s| s.to_vk()).collect();
+
+        // Wait for the previous use of the attachments, e.g. presentation of a swapchain image,
+        // or the depth tests of the previous frame.
+        let dependencies: Vec<_> = desc
+            .subpasses
+            .iter()
+            .enumerate()
+            .filter(|(i, s)| *i == 0 || s.depth_stencil_attachment.is_some())
+            .map(|(i, s)| {
+                let (mut stages, mut access) = (
+                    PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                    AccessFlags::COLOR_ATTACHMENT_WRITE,
+                );
+                if s.depth_stencil_attachment.is_some() {
+                    stages |= PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                        | PipelineStageFlags::LATE_FRAGMENT_TESTS;
+                    access |= AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE;
+                }
+                SubpassDependency::builder()
+                    .src_subpass(SUBPASS_EXTERNAL)
+                    .dst_subpass(i as u32)
+                    .src_stage_mask(stages)
+                    .dst_stage_mask(stages)
+                    .src_access_mask(access & AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                    .dst_access_mask(access)
+                    .build()
+            })
+            .collect();
 
         let create_info = RenderPassCreateInfo::builder()
             .attachments(&attachment_descs)
             .subpasses(&subpass)
-            .dependencies(&[])
+            .dependencies(&dependencies)
             .build();
-        let inner = match unsafe { self.inner.create_render_pass(&create_info, None) } {
-            Ok(r) => r,
-            Err(_) => return Err(GMResult::UnknownError),
-        };
-        Ok(RenderPass { inner })
+        let inner = unsafe { self.inner.create_render_pass(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateRenderPass"))?;
+        Ok(RenderPass {
+            inner,
+            color_attachment_counts: desc
+                .subpasses
+                .iter()
+                .map(|s| s.color_attachments.len())
+                .collect(),
+            device: self.inner.clone(),
+        })
     }
 
-    pub fn create_shader_module(&self, spirv: Spirv, kind: ShaderKind) -> Result<Shader, GMResult> {
+    pub fn create_shader_module(&self, spirv: Spirv, kind: ShaderKind) -> Result<Shader> {
         let shader_create_info = ShaderModuleCreateInfo::builder().code(&spirv.data).build();
-        let shader = match unsafe { self.inner.create_shader_module(&shader_create_info, None) } {
-            Ok(s) => s,
-            Err(_) => panic!("Err"),
-        };
+        let shader = unsafe { self.inner.create_shader_module(&shader_create_info, None) }
+            .map_err(|e| {
+                Error::from_vk(e, "vkCreateShaderModule").with_context(format!("{:?}", kind))
+            })?;
         Ok(Shader {
             inner: shader,
             kind,
+            device: self.inner.clone(),
         })
     }
 
-    pub fn create_fence(&self) -> Result<Fence, GMResult> {
-        let create_info = FenceCreateInfo::builder().build();
-        match unsafe { self.inner.create_fence(&create_info, None) } {
-            Ok(inner) => Ok(Fence { inner }),
-            Err(_) => panic!("Err"),
-        }
+    /// Create an unsignaled fence
+    pub fn create_fence(&self) -> Result<Fence> {
+        self.create_fence_with_flags(FenceCreateFlags::empty())
     }
 
-    #[cfg(feature = "surface")]
-    pub fn create_swapchain(
-        &self,
-        instance: &Instance,
-        device: &Device,
-        gpu: &GPU,
-        surface: &Surface,
-    ) -> Result<Swapchain, GMResult> {
-        use ash::vk::{SurfaceCapabilitiesKHR, SwapchainCreateInfoKHR};
-
-        let surface_capabilities = match unsafe {
-            surface
-                .surface
-                .get_physical_device_surface_capabilities(gpu.device, surface.surface_khr)
-        } {
-            Ok(c) => c,
-            Err(_) => panic!("Err"),
-        };
-        let surface_formats = match unsafe {
-            surface
-                .surface
-                .get_physical_device_surface_formats(gpu.device, surface.surface_khr)
-        } {
-            Ok(f) => f,
-            Err(_) => panic!("Err"),
-        };
-        let surface_present_modes = match unsafe {
-            surface
-                .surface
-                .get_physical_device_surface_present_modes(gpu.device, surface.surface_khr)
-        } {
-            Ok(m) => m,
-            Err(_) => panic!("Err"),
-        };
-        let format = surface_formats[0];
-        let mode = surface_present_modes[0];
-        let create_info = SwapchainCreateInfoKHR::builder()
-            .surface(surface.surface_khr)
-            .min_image_count(surface_capabilities.min_image_count + 1)
-            .image_format(format.format)
-            .image_color_space(format.color_space)
-            .image_extent(surface_capabilities.current_extent)
-            .image_array_layers(1)
-            .image_usage(ImageUsageFlags::COLOR_ATTACHMENT)
-            .image_sharing_mode(SharingMode::EXCLUSIVE)
-            .pre_transform(surface_capabilities.current_transform)
-            .present_mode(mode)
-            .clipped(true)
-            .build();
-        let inner = ash::extensions::khr::Swapchain::new(&instance.instance, &device.inner);
-        let khr = match unsafe { inner.create_swapchain(&create_info, None) } {
-            Ok(k) => k,
-            Err(_) => panic!("Err"),
-        };
-        Ok(Swapchain { inner, khr, format })
+    /// Create a fence that starts signaled, so that the first wait on it returns immediately.
+    pub fn create_signaled_fence(&self) -> Result<Fence> {
+        self.create_fence_with_flags(FenceCreateFlags::SIGNALED)
     }
 
-    #[cfg(feature = "surface")]
-    pub fn acquire_next_image(&self, swapchain: &Swapchain, fence: &Fence) -> usize {
-        match unsafe {
-            swapchain.inner.acquire_next_image(
-                swapchain.khr,
-                1000000000,
-                ash::vk::Semaphore::null(),
-                fence.inner,
-            )
-        } {
-            Ok(i) => return i.0 as usize,
-            Err(_) => panic!("Err"),
-        }
+    fn create_fence_with_flags(&self, flags: FenceCreateFlags) -> Result<Fence> {
+        let create_info = FenceCreateInfo::builder().flags(flags).build();
+        let inner = unsafe { self.inner.create_fence(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateFence"))?;
+        Ok(Fence {
+            inner,
+            device: self.inner.clone(),
+        })
     }
 }
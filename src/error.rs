@@ -0,0 +1,207 @@
+use std::fmt;
+
+/// Result type returned by gallium functions
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Kind of error returned by gallium
+///
+/// # Value Meaning
+/// * `IncompatibleDriver` - Unsupported driver is used.
+/// * `InitializationError` - Initialization failed for some reason.
+/// * `InvalidValue` - Invalid value passed.
+/// * `OutOfMemory` - Out of host or device memory.
+/// * `OutOfPoolMemory` - A pool (e.g. descriptor pool) ran out of memory or is fragmented.
+/// * `TooManyObjects` - Too many objects of the type have already been created.
+/// * `DeviceLost` - The logical or physical device has been lost.
+/// * `MemoryMapFailed` - Mapping of a memory object failed.
+/// * `LayerNotPresent` - A requested layer is not present.
+/// * `ExtensionNotPresent` - A requested extension is not supported.
+/// * `FeatureNotPresent` - A requested feature is not supported.
+/// * `FormatNotSupported` - A requested format is not supported on this device.
+/// * `Unsupported` - The operation is not supported by the device or the surface.
+/// * `NotReady` - A fence or query has not yet completed.
+/// * `Timeout` - A wait operation has not completed in the specified time.
+/// * `Incomplete` - A return array was too small for the result.
+/// * `SurfaceLost` - The surface is no longer available.
+/// * `NativeWindowInUse` - The window is already in use by another API.
+/// * `Suboptimal` - The swapchain no longer matches the surface exactly.
+/// * `OutOfDate` - The swapchain is incompatible with the surface and must be recreated.
+/// * `IncompatibleDisplay` - The display is incompatible with the swapchain.
+/// * `ValidationFailed` - A validation layer found an error.
+/// * `NotPermitted` - The caller does not have sufficient privileges.
+/// * `Io` - Reading or writing a file failed.
+/// * `UnknownError` - Unknown error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    IncompatibleDriver,
+    InitializationError,
+    InvalidValue,
+    OutOfMemory,
+    OutOfPoolMemory,
+    TooManyObjects,
+    DeviceLost,
+    MemoryMapFailed,
+    LayerNotPresent,
+    ExtensionNotPresent,
+    FeatureNotPresent,
+    FormatNotSupported,
+    Unsupported,
+    NotReady,
+    Timeout,
+    Incomplete,
+    SurfaceLost,
+    NativeWindowInUse,
+    Suboptimal,
+    OutOfDate,
+    IncompatibleDisplay,
+    ValidationFailed,
+    NotPermitted,
+    Io,
+    UnknownError,
+}
+
+impl ErrorKind {
+    fn description(&self) -> &'static str {
+        match self {
+            ErrorKind::IncompatibleDriver => "incompatible driver",
+            ErrorKind::InitializationError => "initialization failed",
+            ErrorKind::InvalidValue => "invalid value",
+            ErrorKind::OutOfMemory => "out of memory",
+            ErrorKind::OutOfPoolMemory => "out of pool memory",
+            ErrorKind::TooManyObjects => "too many objects",
+            ErrorKind::DeviceLost => "device lost",
+            ErrorKind::MemoryMapFailed => "memory map failed",
+            ErrorKind::LayerNotPresent => "layer not present",
+            ErrorKind::ExtensionNotPresent => "extension not present",
+            ErrorKind::FeatureNotPresent => "feature not present",
+            ErrorKind::FormatNotSupported => "format not supported",
+            ErrorKind::Unsupported => "unsupported",
+            ErrorKind::NotReady => "not ready",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Incomplete => "incomplete",
+            ErrorKind::SurfaceLost => "surface lost",
+            ErrorKind::NativeWindowInUse => "native window in use",
+            ErrorKind::Suboptimal => "suboptimal swapchain",
+            ErrorKind::OutOfDate => "swapchain out of date",
+            ErrorKind::IncompatibleDisplay => "incompatible display",
+            ErrorKind::ValidationFailed => "validation failed",
+            ErrorKind::NotPermitted => "not permitted",
+            ErrorKind::Io => "I/O error",
+            ErrorKind::UnknownError => "unknown error",
+        }
+    }
+}
+
+/// Error returned by gallium
+///
+/// It carries the [ErrorKind], the raw `VkResult` if the error came from Vulkan,
+/// the failing operation and optional context such as a file path or a format.
+///
+/// # Example
+/// ```
+/// use gallium::{ErrorKind, Spirv};
+///
+/// fn main() {
+///     match Spirv::new("not_found.spv") {
+///         Ok(_) => {}
+///         Err(e) => {
+///             assert_eq!(e.kind(), ErrorKind::Io);
+///             println!("{}", e);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    result: Option<ash::vk::Result>,
+    operation: &'static str,
+    context: Option<String>,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, operation: &'static str) -> Self {
+        Self {
+            kind,
+            result: None,
+            operation,
+            context: None,
+            source: None,
+        }
+    }
+
+    pub(crate) fn from_vk(result: ash::vk::Result, operation: &'static str) -> Self {
+        Self {
+            result: Some(result),
+            ..Self::new(result.into(), operation)
+        }
+    }
+
+    pub(crate) fn from_io(error: std::io::Error, operation: &'static str) -> Self {
+        Self {
+            source: Some(Box::new(error)),
+            ..Self::new(ErrorKind::Io, operation)
+        }
+    }
+
+    /// Keep `source` as the underlying error, e.g. of a decoder.
+    #[cfg_attr(not(any(feature = "png", feature = "jpeg")), allow(dead_code))]
+    pub(crate) fn with_source(
+        mut self,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub(crate) fn with_context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Raw `VkResult` if the error was returned by Vulkan.
+    pub fn vk_result(&self) -> Option<ash::vk::Result> {
+        self.result
+    }
+
+    /// Name of the failing operation, e.g. `vkCreateImage`.
+    pub fn operation(&self) -> &'static str {
+        self.operation
+    }
+
+    /// Additional context such as a file path or a format.
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.operation, self.kind.description())?;
+        if let Some(result) = self.result {
+            write!(f, " ({})", result)?;
+        }
+        if let Some(context) = &self.context {
+            write!(f, " [{}]", context)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|e| e.as_ref() as &(dyn std::error::Error + 'static))
+    }
+}
@@ -1,3 +1,84 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{Error, RawDevice, Result};
+
+/// Synchronizes the CPU with work submitted to a queue
+///
+/// It can be created with create_fence from Device and is signaled when the submission
+/// it was passed to has finished.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Device, Gallium, Queue};
+/// # fn example(device: &Device, queue: &Queue, gallium: &Gallium) {
+///
+/// let fence = device.create_fence().unwrap();
+/// device.dispatch_to_queue_with_fence(gallium, queue, &fence).unwrap();
+/// fence.wait(None).unwrap();
+/// // The GPU has finished; mapped memory can be read.
+/// # }
+/// ```
 pub struct Fence {
     pub(crate) inner: ash::vk::Fence,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_fence(self.inner, None);
+        }
+    }
+}
+
+pub(crate) fn timeout_nanos(timeout: Option<Duration>) -> u64 {
+    timeout.map_or(u64::MAX, |t| t.as_nanos().min(u64::MAX as u128) as u64)
+}
+
+fn wait_for(fences: &[&Fence], wait_all: bool, timeout: Option<Duration>) -> Result<()> {
+    let Some(first) = fences.first() else {
+        return Ok(());
+    };
+    let handles: Vec<_> = fences.iter().map(|f| f.inner).collect();
+    unsafe {
+        first
+            .device
+            .wait_for_fences(&handles, wait_all, timeout_nanos(timeout))
+    }
+    .map_err(|e| Error::from_vk(e, "vkWaitForFences"))
+}
+
+impl Fence {
+    /// Wait until the fence is signaled.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Maximum time to wait. `None` waits forever.
+    ///
+    /// Returns an error of kind `ErrorKind::Timeout` if the time runs out.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<()> {
+        wait_for(&[self], true, timeout)
+    }
+
+    /// Wait until all `fences` are signaled. The fences must belong to the same device.
+    pub fn wait_all(fences: &[&Fence], timeout: Option<Duration>) -> Result<()> {
+        wait_for(fences, true, timeout)
+    }
+
+    /// Wait until at least one of `fences` is signaled. The fences must belong to the same device.
+    pub fn wait_any(fences: &[&Fence], timeout: Option<Duration>) -> Result<()> {
+        wait_for(fences, false, timeout)
+    }
+
+    /// Return the fence to the unsignaled state so that it can be submitted again.
+    pub fn reset(&self) -> Result<()> {
+        unsafe { self.device.reset_fences(&[self.inner]) }
+            .map_err(|e| Error::from_vk(e, "vkResetFences"))
+    }
+
+    /// Returns true if the fence is signaled, without waiting.
+    pub fn is_signaled(&self) -> Result<bool> {
+        unsafe { self.device.get_fence_status(self.inner) }
+            .map_err(|e| Error::from_vk(e, "vkGetFenceStatus"))
+    }
 }
@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use ash::vk::CommandBufferResetFlags;
+
+use crate::{Device, Error, ErrorKind, Fence, Gallium, Queue, Result, Semaphore, SubmitDesc};
+
+/// Resources of one frame in flight
+///
+/// It is handed out by [FrameRing::next_frame].
+pub struct Frame {
+    pub(crate) gallium: Gallium,
+    pub(crate) fence: Fence,
+    pub(crate) image_available: Semaphore,
+    pub(crate) render_finished: Semaphore,
+}
+
+impl Frame {
+    /// Recorder of the frame. It has been reset and is ready for `begin_draw`.
+    pub fn gallium(&self) -> &Gallium {
+        &self.gallium
+    }
+
+    /// Semaphore to pass to `Device::acquire_next_image`.
+    pub fn image_available(&self) -> &Semaphore {
+        &self.image_available
+    }
+
+    /// Semaphore to signal when rendering has finished and to wait on in `Queue::present`.
+    pub fn render_finished(&self) -> &Semaphore {
+        &self.render_finished
+    }
+
+    /// Fence signaled when the GPU has finished the frame.
+    pub fn fence(&self) -> &Fence {
+        &self.fence
+    }
+
+    /// Submit the recorder of the frame
+    ///
+    /// The recorder and the fence of the frame are added to `desc`, and the fence is reset
+    /// just before submission. A frame that is never submitted is simply reused. If the
+    /// submission fails, the fence is signaled again so that [FrameRing::next_frame] does not
+    /// wait for work that never ran.
+    ///
+    /// # Arguments
+    ///
+    /// * `desc` - Semaphores to wait on and signal, usually [Frame::image_available] and [Frame::render_finished].
+    pub fn submit<'a>(
+        &'a self,
+        device: &Device,
+        queue: &Queue,
+        desc: SubmitDesc<'a>,
+    ) -> Result<()> {
+        self.fence.reset()?;
+        let result = device.submit(queue, &desc.gallium(&self.gallium).fence(&self.fence));
+        if result.is_err() {
+            // A failed submission leaves the fence unsignaled; an empty one signals it.
+            if let Err(e) = unsafe {
+                device
+                    .inner
+                    .queue_submit(queue.inner, &[], self.fence.inner)
+            } {
+                log::error!(
+                    "failed to signal the fence of the frame: {}",
+                    Error::from_vk(e, "vkQueueSubmit")
+                );
+            }
+        }
+        result
+    }
+}
+
+/// Ring of frames that the CPU records while the GPU renders the previous ones
+///
+/// It can be created with create_frame_ring from Device.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Device, PipelineStageFlags, Queue, SubmitDesc, Swapchain};
+/// # fn example(device: &Device, queue: &Queue, swapchain: &Swapchain) {
+///
+/// let mut frames = device.create_frame_ring(queue, 2).unwrap();
+/// loop {
+///     let frame = frames.next_frame().unwrap();
+///     let Some(index) = device
+///         .acquire_next_image(swapchain, Some(frame.image_available()), None)
+///         .unwrap()
+///         .index()
+///     else {
+///         break; // Recreate the swapchain
+///     };
+///     let gallium = frame.gallium();
+///     gallium.begin_draw(device).unwrap();
+///     // record the frame ...
+///     gallium.end_draw(device).unwrap();
+///     frame
+///         .submit(
+///             device,
+///             queue,
+///             SubmitDesc::new()
+///                 .wait(frame.image_available(), PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+///                 .signal(frame.render_finished()),
+///         )
+///         .unwrap();
+///     queue.present(swapchain, index, &[frame.render_finished()]).unwrap();
+/// }
+/// # }
+/// ```
+pub struct FrameRing {
+    pub(crate) frames: Vec<Frame>,
+    pub(crate) current: usize,
+}
+
+impl FrameRing {
+    /// Number of frames in flight.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Index of the frame returned by the last [FrameRing::next_frame].
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Advance to the next frame
+    ///
+    /// Waits until the GPU has finished the previous use of the frame, then resets its recorder.
+    pub fn next_frame(&mut self) -> Result<&Frame> {
+        self.next_frame_timeout(None)
+    }
+
+    /// [FrameRing::next_frame] that gives up after `timeout` with `ErrorKind::Timeout`.
+    pub fn next_frame_timeout(&mut self, timeout: Option<Duration>) -> Result<&Frame> {
+        let next = (self.current + 1) % self.frames.len();
+        let frame = &self.frames[next];
+        frame.fence.wait(timeout)?;
+        unsafe {
+            frame.gallium.device.reset_command_buffer(
+                frame.gallium.command_buffer,
+                CommandBufferResetFlags::empty(),
+            )
+        }
+        .map_err(|e| Error::from_vk(e, "vkResetCommandBuffer"))?;
+        self.current = next;
+        Ok(frame)
+    }
+}
+
+impl Device {
+    /// Create a ring of frames in flight
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - Queue the frames are submitted to.
+    /// * `frames_in_flight` - Number of frames, usually 2 or 3.
+    pub fn create_frame_ring(&self, queue: &Queue, frames_in_flight: usize) -> Result<FrameRing> {
+        if frames_in_flight == 0 {
+            return Err(
+                Error::new(ErrorKind::InvalidValue, "Device::create_frame_ring")
+                    .with_context("frames_in_flight must be at least 1"),
+            );
+        }
+        let mut frames = Vec::with_capacity(frames_in_flight);
+        for _ in 0..frames_in_flight {
+            frames.push(Frame {
+                gallium: self.create_gallium(queue)?,
+                // Signaled so that the first wait on each frame returns immediately.
+                fence: self.create_signaled_fence()?,
+                image_available: self.create_semaphore()?,
+                render_finished: self.create_semaphore()?,
+            });
+        }
+        Ok(FrameRing {
+            frames,
+            // The first call to next_frame returns frame 0.
+            current: frames_in_flight - 1,
+        })
+    }
+}
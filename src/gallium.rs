@@ -1,25 +1,46 @@
+use std::sync::Arc;
+
+pub use ash::vk::{IndexType, Offset2D, Rect2D, Viewport};
+
 use ash::vk::{
     CommandBuffer, CommandBufferBeginInfo, CommandBufferResetFlags, CommandPool, Extent2D,
-    Offset2D, PipelineBindPoint, Rect2D, RenderPassBeginInfo, SubpassContents,
+    RenderPassBeginInfo, ShaderStageFlags, SubpassContents,
 };
+use bytemuck::Pod;
 
-use crate::{Device, FrameBuffer, Pipeline, RenderPass};
+use crate::{
+    check_push_constant_range, Buffer, ClearValue, DescriptorSet, Device, Error, FrameBuffer,
+    Pipeline, RawDevice, RenderPass, Result,
+};
 
+/// Records commands into a command buffer
+///
+/// It can be created with create_gallium from Device.
 pub struct Gallium {
     pub(crate) command_pool: CommandPool,
-    pub(crate) command_buffers: Vec<CommandBuffer>,
+    pub(crate) command_buffer: CommandBuffer,
+    pub(crate) device: Arc<RawDevice>,
 }
 
 impl Gallium {
-    pub fn begin_draw(&self, device: &Device) {
+    pub fn begin_draw(&self, device: &Device) -> Result<()> {
         let begin_info = CommandBufferBeginInfo::builder().build();
         unsafe {
             device
                 .inner
-                .begin_command_buffer(self.command_buffers[0], &begin_info)
-        };
+                .begin_command_buffer(self.command_buffer, &begin_info)
+        }
+        .map_err(|e| Error::from_vk(e, "vkBeginCommandBuffer"))
     }
 
+    /// Begin a render pass
+    ///
+    /// The viewport and the scissor are set to the whole `width` x `height` render area.
+    /// Use [Gallium::set_viewport] and [Gallium::set_scissor] to draw to a part of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `clear_values` - One value per attachment, used by attachments loaded with `AttachmentLoadOp::CLEAR`.
     pub fn begin_render_pass(
         &self,
         device: &Device,
@@ -27,58 +48,345 @@ impl Gallium {
         render_pass: &RenderPass,
         width: u32,
         height: u32,
+        clear_values: &[ClearValue],
     ) {
+        let clear_values: Vec<ash::vk::ClearValue> =
+            clear_values.iter().map(|c| (*c).into()).collect();
+        let render_area = Rect2D::builder()
+            .extent(Extent2D::builder().width(width).height(height).build())
+            .offset(Offset2D::builder().x(0).y(0).build())
+            .build();
         let render_pass_begin = RenderPassBeginInfo::builder()
             .render_pass(render_pass.inner)
             .framebuffer(frame_buffer.inner)
-            .render_area(
-                Rect2D::builder()
-                    .extent(Extent2D::builder().width(width).height(height).build())
-                    .offset(Offset2D::builder().x(0).y(0).build())
-                    .build(),
-            )
-            .clear_values(&[])
+            .render_area(render_area)
+            .clear_values(&clear_values)
             .build();
         unsafe {
             device.inner.cmd_begin_render_pass(
-                self.command_buffers[0],
+                self.command_buffer,
                 &render_pass_begin,
                 SubpassContents::INLINE,
             );
         }
+        self.set_viewport(
+            device,
+            Viewport::builder()
+                .x(0.0)
+                .y(0.0)
+                .width(width as f32)
+                .height(height as f32)
+                .min_depth(0.0)
+                .max_depth(1.0)
+                .build(),
+        );
+        self.set_scissor(device, render_area);
+    }
+
+    /// Set the viewport of the following draws.
+    pub fn set_viewport(&self, device: &Device, viewport: Viewport) {
+        unsafe {
+            device
+                .inner
+                .cmd_set_viewport(self.command_buffer, 0, &[viewport]);
+        }
+    }
+
+    /// Set the rectangle outside of which the following draws discard fragments.
+    pub fn set_scissor(&self, device: &Device, scissor: Rect2D) {
+        unsafe {
+            device
+                .inner
+                .cmd_set_scissor(self.command_buffer, 0, &[scissor]);
+        }
     }
 
     pub fn end_render_pass(&self, device: &Device) {
         unsafe {
-            device.inner.cmd_end_render_pass(self.command_buffers[0]);
+            device.inner.cmd_end_render_pass(self.command_buffer);
         }
     }
 
-    pub fn reset(&self, device: &Device) {
+    pub fn reset(&self, device: &Device) -> Result<()> {
         unsafe {
             device
                 .inner
-                .reset_command_buffer(self.command_buffers[0], CommandBufferResetFlags::empty());
+                .reset_command_buffer(self.command_buffer, CommandBufferResetFlags::empty())
         }
+        .map_err(|e| Error::from_vk(e, "vkResetCommandBuffer"))
     }
 
     pub fn bind_pipeline(&self, device: &Device, pipeline: &Pipeline) {
         unsafe {
             device.inner.cmd_bind_pipeline(
-                self.command_buffers[0],
-                PipelineBindPoint::GRAPHICS,
+                self.command_buffer,
+                pipeline.bind_point,
                 pipeline.inner,
             );
         }
     }
 
-    pub fn draw(&self, device: &Device, a: u32, b: u32, c: u32, d: u32) {
+    /// Bind descriptor sets
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - Pipeline whose layout the sets are compatible with.
+    /// * `first_set` - Set number of the first set. The following sets use the next numbers.
+    /// * `sets` - Descriptor sets to bind.
+    pub fn bind_descriptor_sets(
+        &self,
+        device: &Device,
+        pipeline: &Pipeline,
+        first_set: u32,
+        sets: &[&DescriptorSet],
+    ) {
+        let sets: Vec<_> = sets.iter().map(|s| s.inner).collect();
+        unsafe {
+            device.inner.cmd_bind_descriptor_sets(
+                self.command_buffer,
+                pipeline.bind_point,
+                pipeline.layout,
+                first_set,
+                &sets,
+                &[],
+            );
+        }
+    }
+
+    /// Update push constants
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - Pipeline whose layout declares the push constant range.
+    /// * `stages` - Shader stages of the range.
+    /// * `offset` - Offset in bytes of `value` in the range.
+    /// * `value` - Data to be written. Its size must not exceed `max_push_constants_size`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gallium::{Device, Gallium, Pipeline, ShaderStageFlags};
+    /// # fn example(gallium: &Gallium, device: &Device, pipeline: &Pipeline) {
+    ///
+    /// let color: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    /// gallium
+    ///     .push_constants(device, pipeline, ShaderStageFlags::FRAGMENT, 0, &color)
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn push_constants<T: Pod>(
+        &self,
+        device: &Device,
+        pipeline: &Pipeline,
+        stages: ShaderStageFlags,
+        offset: u32,
+        value: &T,
+    ) -> Result<()> {
+        let bytes = bytemuck::bytes_of(value);
+        check_push_constant_range(
+            &device.inner,
+            offset,
+            bytes.len(),
+            "Gallium::push_constants",
+        )?;
+        unsafe {
+            device.inner.cmd_push_constants(
+                self.command_buffer,
+                pipeline.layout,
+                stages,
+                offset,
+                bytes,
+            );
+        }
+        Ok(())
+    }
+
+    /// Bind vertex buffers
+    ///
+    /// # Arguments
+    ///
+    /// * `first_binding` - Binding of the first buffer. The following buffers use the next bindings.
+    /// * `buffers` - Buffers and the offsets in bytes at which their vertices start.
+    pub fn bind_vertex_buffers(
+        &self,
+        device: &Device,
+        first_binding: u32,
+        buffers: &[(&Buffer, u64)],
+    ) {
+        let (handles, offsets): (Vec<_>, Vec<_>) =
+            buffers.iter().map(|(b, offset)| (b.inner, *offset)).unzip();
+        unsafe {
+            device.inner.cmd_bind_vertex_buffers(
+                self.command_buffer,
+                first_binding,
+                &handles,
+                &offsets,
+            );
+        }
+    }
+
+    /// Bind an index buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Offset in bytes at which the indices start.
+    /// * `index_type` - `IndexType::UINT16` or `IndexType::UINT32`.
+    pub fn bind_index_buffer(
+        &self,
+        device: &Device,
+        buffer: &Buffer,
+        offset: u64,
+        index_type: IndexType,
+    ) {
+        unsafe {
+            device.inner.cmd_bind_index_buffer(
+                self.command_buffer,
+                buffer.inner,
+                offset,
+                index_type,
+            );
+        }
+    }
+
+    /// Draw primitives
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_count` - Number of vertices to draw.
+    /// * `instance_count` - Number of instances to draw.
+    /// * `first_vertex` - Index of the first vertex.
+    /// * `first_instance` - Instance ID of the first instance.
+    pub fn draw(
+        &self,
+        device: &Device,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            device.inner.cmd_draw(
+                self.command_buffer,
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            );
+        }
+    }
+
+    /// Draw `instance_count` instances of `vertex_count` vertices.
+    pub fn draw_instanced(&self, device: &Device, vertex_count: u32, instance_count: u32) {
+        self.draw(device, vertex_count, instance_count, 0, 0);
+    }
+
+    /// Draw primitives with the bound index buffer
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gallium::{Buffer, Device, DrawIndexed, Gallium, IndexType};
+    /// # fn example(gallium: &Gallium, device: &Device, vertex_buffer: &Buffer,
+    /// #     index_buffer: &Buffer) {
+    ///
+    /// gallium.bind_vertex_buffers(device, 0, &[(vertex_buffer, 0)]);
+    /// gallium.bind_index_buffer(device, index_buffer, 0, IndexType::UINT16);
+    /// gallium.draw_indexed(device, &DrawIndexed::new(36).instances(100, 0));
+    /// # }
+    /// ```
+    pub fn draw_indexed(&self, device: &Device, draw: &DrawIndexed) {
         unsafe {
-            device.inner.cmd_draw(self.command_buffers[0], a, b, c, d);
+            device.inner.cmd_draw_indexed(
+                self.command_buffer,
+                draw.index_count,
+                draw.instance_count,
+                draw.first_index,
+                draw.vertex_offset,
+                draw.first_instance,
+            );
         }
     }
 
-    pub fn end_draw(&self, device: &Device) {
-        unsafe { device.inner.end_command_buffer(self.command_buffers[0]) };
+    /// Dispatch compute work groups with the bound compute pipeline
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y`, `z` - Number of work groups in each dimension.
+    pub fn dispatch(&self, device: &Device, x: u32, y: u32, z: u32) {
+        unsafe {
+            device.inner.cmd_dispatch(self.command_buffer, x, y, z);
+        }
+    }
+
+    /// Dispatch compute work groups whose counts are read from `buffer`
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - Buffer created with `BufferUsageFlags::INDIRECT_BUFFER`.
+    /// * `offset` - Offset in bytes of three `u32` work group counts in the buffer.
+    pub fn dispatch_indirect(&self, device: &Device, buffer: &Buffer, offset: u64) {
+        unsafe {
+            device
+                .inner
+                .cmd_dispatch_indirect(self.command_buffer, buffer.inner, offset);
+        }
+    }
+
+    pub fn end_draw(&self, device: &Device) -> Result<()> {
+        unsafe { device.inner.end_command_buffer(self.command_buffer) }
+            .map_err(|e| Error::from_vk(e, "vkEndCommandBuffer"))
+    }
+}
+
+/// Parameters of an indexed draw
+///
+/// # Value Meaning
+/// * `index_count` - Number of indices to draw.
+/// * `instance_count` - Number of instances to draw.
+/// * `first_index` - Position of the first index in the index buffer.
+/// * `vertex_offset` - Value added to each index before reading the vertex buffers.
+/// * `first_instance` - Instance ID of the first instance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawIndexed {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub vertex_offset: i32,
+    pub first_instance: u32,
+}
+
+impl DrawIndexed {
+    /// Draw a single instance of `index_count` indices from the start of the index buffer.
+    pub fn new(index_count: u32) -> Self {
+        Self {
+            index_count,
+            instance_count: 1,
+            first_index: 0,
+            vertex_offset: 0,
+            first_instance: 0,
+        }
+    }
+
+    pub fn instances(mut self, instance_count: u32, first_instance: u32) -> Self {
+        self.instance_count = instance_count;
+        self.first_instance = first_instance;
+        self
+    }
+
+    pub fn first_index(mut self, first_index: u32) -> Self {
+        self.first_index = first_index;
+        self
+    }
+
+    pub fn vertex_offset(mut self, vertex_offset: i32) -> Self {
+        self.vertex_offset = vertex_offset;
+        self
+    }
+}
+
+impl Drop for Gallium {
+    fn drop(&mut self) {
+        // Command buffers are freed together with the pool.
+        unsafe {
+            self.device.destroy_command_pool(self.command_pool, None);
+        }
     }
 }
@@ -1,268 +1,928 @@
-use std::ffi::{c_void, CStr, CString};
+use std::sync::Arc;
+
+pub use ash::vk::{
+    AttachmentLoadOp, AttachmentStoreOp, ComponentMapping, ComponentSwizzle, Extent2D, Extent3D,
+    Format, ImageAspectFlags, ImageCreateFlags, ImageLayout, ImageTiling, ImageType,
+    ImageUsageFlags, ImageViewType, SampleCountFlags, REMAINING_ARRAY_LAYERS, REMAINING_MIP_LEVELS,
+};
 
 use ash::vk::{
-    AttachmentReference, ColorComponentFlags, ComponentMapping, ComponentSwizzle, CullModeFlags,
-    Format, FramebufferCreateInfo, FrontFace, GraphicsPipelineCreateInfo, ImageAspectFlags,
-    ImageLayout, ImageSubresourceRange, ImageViewCreateInfo, ImageViewType, MemoryMapFlags,
-    MemoryRequirements, PipelineBindPoint, PipelineCache, PipelineColorBlendAttachmentState,
-    PipelineColorBlendStateCreateInfo, PipelineInputAssemblyStateCreateInfo,
-    PipelineLayoutCreateInfo, PipelineMultisampleStateCreateInfo,
-    PipelineRasterizationStateCreateInfo, PipelineShaderStageCreateInfo,
-    PipelineVertexInputStateCreateInfo, PipelineViewportStateCreateInfo, PolygonMode,
-    PrimitiveTopology, Rect2D, SampleCountFlags, ShaderStageFlags, SubpassDescription,
+    AccessFlags, AttachmentDescription, AttachmentReference, BufferImageCopy, ClearColorValue,
+    ClearDepthStencilValue, DependencyFlags, FramebufferCreateInfo, ImageMemoryBarrier,
+    ImageSubresource, ImageSubresourceLayers, ImageSubresourceRange, ImageViewCreateInfo,
+    PipelineBindPoint, PipelineStageFlags, SubpassDescription, QUEUE_FAMILY_IGNORED,
+};
+
+use crate::{
+    Allocation, BufferDesc, BufferUsageFlags, Device, Error, ErrorKind, MappedMemory,
+    MemoryLocation, Queue, RawDevice, Result,
 };
 
-use crate::{Device, GMResult};
-use crate::{Pipeline, Shader};
+/// Description for Image Creation
+///
+/// The default is a single 1x1 `R8G8B8A8_UNORM` 2D texel with OPTIMAL tiling in
+/// device-local memory that can be sampled and written by transfers.
+///
+/// # Value Meaning
+/// * `image_type` - 1D, 2D or 3D. 1D images have a height and depth of 1, 2D images a depth of 1.
+/// * `extent` - Size in texels.
+/// * `mip_levels` - Number of mip levels, at least 1.
+/// * `array_layers` - Number of layers, e.g. 6 for a cube map. 3D images have 1 layer.
+/// * `flags` - `CUBE_COMPATIBLE` for square 2D images with at least 6 layers viewed as cube maps,
+///   `MUTABLE_FORMAT` for images viewed with another compatible format.
+/// * `samples` - Samples per texel. Multisampled images are 2D, OPTIMAL and have 1 mip level.
+/// * `tiling` - OPTIMAL for the GPU, LINEAR to access the texels from the CPU.
+/// * `location` - Where the memory is placed. LINEAR images in host-visible memory can be mapped.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Device, Extent3D, Format, ImageDesc, ImageUsageFlags, SampleCountFlags};
+/// # fn example(device: &Device) {
+///
+/// // 4x multisampled color attachment
+/// let image = device
+///     .create_image_with_desc(&ImageDesc {
+///         format: Format::B8G8R8A8_SRGB,
+///         extent: Extent3D { width: 640, height: 480, depth: 1 },
+///         samples: SampleCountFlags::TYPE_4,
+///         usage: ImageUsageFlags::COLOR_ATTACHMENT,
+///         ..Default::default()
+///     })
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageDesc {
+    pub image_type: ImageType,
+    pub format: Format,
+    pub extent: Extent3D,
+    pub mip_levels: u32,
+    pub array_layers: u32,
+    pub flags: ImageCreateFlags,
+    pub samples: SampleCountFlags,
+    pub tiling: ImageTiling,
+    pub usage: ImageUsageFlags,
+    pub location: MemoryLocation,
+}
+
+impl Default for ImageDesc {
+    fn default() -> Self {
+        Self {
+            image_type: ImageType::TYPE_2D,
+            format: Format::R8G8B8A8_UNORM,
+            extent: Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            flags: ImageCreateFlags::empty(),
+            samples: SampleCountFlags::TYPE_1,
+            tiling: ImageTiling::OPTIMAL,
+            usage: ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_DST,
+            location: MemoryLocation::GpuOnly,
+        }
+    }
+}
+
+impl ImageDesc {
+    /// Check the description against the rules of Vulkan and the limits of the GPU.
+    pub(crate) fn validate(&self, device: &RawDevice) -> Result<()> {
+        const OPERATION: &str = "Device::create_image_with_desc";
+        let invalid = |context: &str| {
+            Err(Error::new(ErrorKind::InvalidValue, OPERATION)
+                .with_context(format!("{}: {:?}", context, self)))
+        };
+        let Extent3D {
+            width,
+            height,
+            depth,
+        } = self.extent;
+        if width == 0 || height == 0 || depth == 0 {
+            return invalid("the extent is empty");
+        }
+        if self.mip_levels == 0 || self.array_layers == 0 {
+            return invalid("an image needs at least one mip level and one layer");
+        }
+        match self.image_type {
+            ImageType::TYPE_1D if height != 1 || depth != 1 => {
+                return invalid("1D images have a height and depth of 1")
+            }
+            ImageType::TYPE_2D if depth != 1 => return invalid("2D images have a depth of 1"),
+            ImageType::TYPE_3D if self.array_layers != 1 => {
+                return invalid("3D images have a single layer")
+            }
+            _ => (),
+        }
+        if self.samples != SampleCountFlags::TYPE_1
+            && (self.image_type != ImageType::TYPE_2D
+                || self.mip_levels != 1
+                || self.tiling != ImageTiling::OPTIMAL)
+        {
+            return invalid("multisampled images are 2D, OPTIMAL and have one mip level");
+        }
+        if self.flags.contains(ImageCreateFlags::CUBE_COMPATIBLE)
+            && (self.image_type != ImageType::TYPE_2D || width != height || self.array_layers < 6)
+        {
+            return invalid("cube compatible images are square, 2D and have at least 6 layers");
+        }
+
+        let properties = unsafe {
+            device.instance.get_physical_device_image_format_properties(
+                device.gpu,
+                self.format,
+                self.image_type,
+                self.tiling,
+                self.usage,
+                self.flags,
+            )
+        }
+        .map_err(|e| {
+            Error::from_vk(e, "vkGetPhysicalDeviceImageFormatProperties").with_context(format!(
+                "{:?} with {:?} tiling and {:?}",
+                self.format, self.tiling, self.usage
+            ))
+        })?;
+        let max = properties.max_extent;
+        if width > max.width || height > max.height || depth > max.depth {
+            return invalid("the extent exceeds the limit of the format");
+        }
+        if self.mip_levels > properties.max_mip_levels {
+            return invalid("too many mip levels");
+        }
+        if self.array_layers > properties.max_array_layers {
+            return invalid("too many layers");
+        }
+        if !properties.sample_counts.contains(self.samples) {
+            return Err(Error::new(ErrorKind::FormatNotSupported, OPERATION)
+                .with_context(format!("{:?} with {:?}", self.format, self.samples)));
+        }
+        Ok(())
+    }
+}
 
 pub struct Image {
-    pub(crate) viewport: ash::vk::Viewport,
-    pub(crate) scissors: Vec<Rect2D>,
-    pub(crate) memory: ash::vk::DeviceMemory,
-    pub(crate) img_mem_required: MemoryRequirements,
+    pub(crate) allocation: Allocation,
+    pub(crate) desc: ImageDesc,
     pub(crate) inner: ash::vk::Image,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_image(self.inner, None);
+        }
+        self.device.free(&self.allocation);
+    }
 }
 
 impl Image {
-    /// Create an image.
+    /// Create a view of the whole image.
     ///
     /// # Arguments
     ///
     /// * `device` - Valid Devices
-    pub fn create_image_view(&self, device: &Device) -> Result<ImageView, GMResult> {
+    pub fn create_image_view(&self, device: &Device) -> Result<ImageView> {
+        self.create_image_view_with_desc(device, &ImageViewDesc::default())
+    }
+
+    /// Create a view of a part of the image, or one that reinterprets it
+    ///
+    /// Several views of one image can be created, e.g. one per layer of an array.
+    ///
+    /// # Arguments
+    ///
+    /// * `desc` - Type, format, subresources and swizzle of the view.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gallium::{Device, Image, ImageViewDesc};
+    /// # fn example(device: &Device, image: &Image) {
+    ///
+    /// // View of the layer 2 of an array image
+    /// let view = image
+    ///     .create_image_view_with_desc(
+    ///         device,
+    ///         &ImageViewDesc {
+    ///             base_array_layer: 2,
+    ///             layer_count: 1,
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn create_image_view_with_desc(
+        &self,
+        device: &Device,
+        desc: &ImageViewDesc,
+    ) -> Result<ImageView> {
+        let range = desc.subresource_range(&self.desc)?;
+        let view_type = desc
+            .view_type
+            .unwrap_or_else(|| default_view_type(self.desc.image_type, range.layer_count));
+        let format = desc.format.unwrap_or(self.desc.format);
+        let invalid = |context: &str| {
+            Err(Error::new(
+                ErrorKind::InvalidValue,
+                "Image::create_image_view_with_desc",
+            )
+            .with_context(context.to_string()))
+        };
+        if matches!(view_type, ImageViewType::CUBE | ImageViewType::CUBE_ARRAY)
+            && !self.desc.flags.contains(ImageCreateFlags::CUBE_COMPATIBLE)
+        {
+            return invalid("cube views need an image created with CUBE_COMPATIBLE");
+        }
+        if format != self.desc.format && !self.desc.flags.contains(ImageCreateFlags::MUTABLE_FORMAT)
+        {
+            return invalid(&format!(
+                "viewing a {:?} image as {:?} needs MUTABLE_FORMAT",
+                self.desc.format, format
+            ));
+        }
         let create_info = ImageViewCreateInfo::builder()
             .image(self.inner)
-            .view_type(ImageViewType::TYPE_2D)
-            .format(Format::R8G8B8A8_UNORM)
-            .components(
-                ComponentMapping::builder()
-                    .a(ComponentSwizzle::IDENTITY)
-                    .r(ComponentSwizzle::IDENTITY)
-                    .g(ComponentSwizzle::IDENTITY)
-                    .b(ComponentSwizzle::IDENTITY)
-                    .build(),
-            )
-            .subresource_range(
-                ImageSubresourceRange::builder()
-                    .aspect_mask(ImageAspectFlags::COLOR)
-                    .base_mip_level(0)
-                    .level_count(1)
-                    .base_array_layer(0)
-                    .layer_count(1)
-                    .build(),
-            )
+            .view_type(view_type)
+            .format(format)
+            .components(desc.components)
+            .subresource_range(range)
             .build();
-        let inner = match unsafe { device.inner.create_image_view(&create_info, None) } {
-            Ok(i) => i,
-            Err(_) => return Err(GMResult::UnknownError),
+        let inner = unsafe { device.inner.create_image_view(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateImageView"))?;
+        Ok(ImageView {
+            inner,
+            device: device.inner.clone(),
+        })
+    }
+
+    /// Description the image was created with.
+    pub fn desc(&self) -> &ImageDesc {
+        &self.desc
+    }
+
+    /// Format of the texels.
+    pub fn format(&self) -> Format {
+        self.desc.format
+    }
+
+    /// Width and height of the image.
+    pub fn extent(&self) -> Extent2D {
+        Extent2D {
+            width: self.desc.extent.width,
+            height: self.desc.extent.height,
+        }
+    }
+
+    /// Read the texels of the image back to the CPU
+    ///
+    /// Returns tightly packed rows of texels of mip level 0 of the first layer, from the top row
+    /// down. `B8G8R8A8` images are converted to RGBA, other formats keep their texel layout.
+    /// Host-visible linear images in the `GENERAL` or `PREINITIALIZED` layout are read in place
+    /// after waiting for `queue` to be idle, honouring the row pitch of the driver. Other images
+    /// are copied to a staging buffer and need the `TRANSFER_SRC` usage.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - Queue that rendered to the image.
+    /// * `layout` - Current layout of the image, e.g. the final layout of the render pass that
+    ///   drew it. The image is left in this layout. It cannot be `UNDEFINED`, and only
+    ///   host-readable images can be `PREINITIALIZED`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gallium::{Device, Image, ImageLayout, Queue};
+    /// # fn example(image: &Image, device: &Device, queue: &Queue) {
+    ///
+    /// // Rendered by a render pass with the default `AttachmentDesc::final_layout`
+    /// let pixels = image
+    ///     .read_pixels(device, queue, ImageLayout::GENERAL)
+    ///     .unwrap();
+    /// let extent = image.extent();
+    /// let w = std::io::BufWriter::new(std::fs::File::create("image.png").unwrap());
+    /// let mut encoder = png::Encoder::new(w, extent.width, extent.height);
+    /// encoder.set_color(png::ColorType::Rgba);
+    /// encoder.write_header().unwrap().write_image_data(&pixels).unwrap();
+    /// # }
+    /// ```
+    pub fn read_pixels(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        layout: ImageLayout,
+    ) -> Result<Vec<u8>> {
+        let format = self.format();
+        let extent = self.extent();
+        let texel_size = texel_size(format).ok_or_else(|| {
+            Error::new(ErrorKind::FormatNotSupported, "Image::read_pixels")
+                .with_context(format!("{:?} cannot be read", format))
+        })?;
+        if self.desc.samples != SampleCountFlags::TYPE_1 {
+            return Err(Error::new(ErrorKind::InvalidValue, "Image::read_pixels")
+                .with_context("multisampled images cannot be read, resolve them first"));
+        }
+        if layout == ImageLayout::UNDEFINED {
+            return Err(Error::new(ErrorKind::InvalidValue, "Image::read_pixels")
+                .with_context("the contents of an image in the UNDEFINED layout are undefined"));
+        }
+        let row_size = (extent.width as u64 * texel_size) as usize;
+        let size = row_size * extent.height as usize;
+
+        // The host may only access linear images in these layouts.
+        let host_readable = self.desc.tiling == ImageTiling::LINEAR
+            && self.allocation.mapped_ptr().is_some()
+            && matches!(layout, ImageLayout::GENERAL | ImageLayout::PREINITIALIZED);
+        let mut pixels = if host_readable {
+            // Wait for the rendering to the image.
+            unsafe { device.inner.queue_wait_idle(queue.inner) }
+                .map_err(|e| Error::from_vk(e, "vkQueueWaitIdle"))?;
+            let subresource = unsafe {
+                device.inner.get_image_subresource_layout(
+                    self.inner,
+                    ImageSubresource {
+                        aspect_mask: ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        array_layer: 0,
+                    },
+                )
+            };
+            let memory = MappedMemory::new(
+                &self.device,
+                &self.allocation,
+                self.allocation.size,
+                "Image::read_pixels",
+            )?;
+            let bytes = memory.as_bytes();
+            let mut pixels = Vec::with_capacity(size);
+            for y in 0..extent.height as u64 {
+                let start = (subresource.offset + y * subresource.row_pitch) as usize;
+                pixels.extend_from_slice(&bytes[start..start + row_size]);
+            }
+            pixels
+        } else {
+            self.copy_to_cpu(device, queue, layout, size as u64)?
         };
-        Ok(ImageView { inner })
+
+        if matches!(format, Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB) {
+            for texel in pixels.chunks_exact_mut(4) {
+                texel.swap(0, 2);
+            }
+        }
+        Ok(pixels)
     }
 
-    pub fn map_memory(&self, device: &Device) -> *mut c_void {
-        unsafe {
-            device
-                .inner
-                .map_memory(
-                    self.memory,
-                    0,
-                    self.img_mem_required.size,
-                    MemoryMapFlags::empty(),
+    /// Copy the image into a tightly packed staging buffer and read it.
+    fn copy_to_cpu(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        layout: ImageLayout,
+        size: u64,
+    ) -> Result<Vec<u8>> {
+        // The image cannot be transitioned back to it.
+        if layout == ImageLayout::PREINITIALIZED {
+            return Err(Error::new(ErrorKind::InvalidValue, "Image::read_pixels")
+                .with_context("only linear host-visible images can be read in PREINITIALIZED"));
+        }
+        if !self.desc.usage.contains(ImageUsageFlags::TRANSFER_SRC) {
+            return Err(
+                Error::new(ErrorKind::InvalidValue, "Image::read_pixels").with_context(format!(
+                    "reading through a staging buffer needs TRANSFER_SRC usage, got {:?}",
+                    self.desc.usage
+                )),
+            );
+        }
+        let mut staging = device.create_buffer(&BufferDesc {
+            size,
+            usage: BufferUsageFlags::TRANSFER_DST,
+            location: MemoryLocation::GpuToCpu,
+        })?;
+        let to_transfer = layout_barrier(
+            self.inner,
+            layout,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            AccessFlags::MEMORY_WRITE,
+            AccessFlags::TRANSFER_READ,
+        );
+        let to_previous = layout_barrier(
+            self.inner,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            layout,
+            AccessFlags::TRANSFER_READ,
+            AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE,
+        );
+        let extent = self.extent();
+        let region = color_copy_region(extent.width, extent.height);
+        device.run_commands(queue, |command_buffer| unsafe {
+            device.inner.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::ALL_COMMANDS,
+                PipelineStageFlags::TRANSFER,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer],
+            );
+            device.inner.cmd_copy_image_to_buffer(
+                command_buffer,
+                self.inner,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                staging.inner,
+                &[region],
+            );
+            device.inner.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::ALL_COMMANDS,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_previous],
+            );
+        })?;
+        let mut pixels = vec![0; size as usize];
+        staging.read(0, &mut pixels)?;
+        Ok(pixels)
+    }
+
+    /// Map the memory of the image for CPU access
+    ///
+    /// Only images created with `Device::create_image` are host visible.
+    /// The GPU must have finished writing to the image.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn example(image: &mut gallium::Image) {
+    /// let memory = image.map_memory().unwrap();
+    /// let bytes: &[u8] = memory.as_bytes();
+    /// # }
+    /// ```
+    pub fn map_memory(&mut self) -> Result<MappedMemory<'_>> {
+        MappedMemory::new(
+            &self.device,
+            &self.allocation,
+            self.allocation.size,
+            "Image::map_memory",
+        )
+    }
+}
+
+/// Description for Image View Creation
+///
+/// The default views the whole image with its own format and no swizzle.
+///
+/// # Value Meaning
+/// * `view_type` - None derives the type from the image, e.g. `TYPE_2D_ARRAY` for a 2D image viewed with several layers. Cube views need a `CUBE_COMPATIBLE` image.
+/// * `format` - None uses the format of the image. Other formats must be compatible with it and need a `MUTABLE_FORMAT` image.
+/// * `aspect` - None derives the aspect from the format: depth and stencil for depth formats, otherwise color.
+/// * `base_mip_level` - First mip level of the view.
+/// * `level_count` - Number of mip levels, `REMAINING_MIP_LEVELS` for all from `base_mip_level`.
+/// * `base_array_layer` - First layer of the view.
+/// * `layer_count` - Number of layers, `REMAINING_ARRAY_LAYERS` for all from `base_array_layer`.
+/// * `components` - Swizzle of the components, e.g. to read a single-channel image as gray.
+///
+/// # Example
+/// ```
+/// use gallium::{ComponentMapping, ComponentSwizzle, ImageViewDesc};
+///
+/// // Read the red channel as gray
+/// let desc = ImageViewDesc {
+///     components: ComponentMapping {
+///         r: ComponentSwizzle::R,
+///         g: ComponentSwizzle::R,
+///         b: ComponentSwizzle::R,
+///         a: ComponentSwizzle::ONE,
+///     },
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ImageViewDesc {
+    pub view_type: Option<ImageViewType>,
+    pub format: Option<Format>,
+    pub aspect: Option<ImageAspectFlags>,
+    pub base_mip_level: u32,
+    pub level_count: u32,
+    pub base_array_layer: u32,
+    pub layer_count: u32,
+    pub components: ComponentMapping,
+}
+
+impl Default for ImageViewDesc {
+    fn default() -> Self {
+        Self {
+            view_type: None,
+            format: None,
+            aspect: None,
+            base_mip_level: 0,
+            level_count: REMAINING_MIP_LEVELS,
+            base_array_layer: 0,
+            layer_count: REMAINING_ARRAY_LAYERS,
+            components: ComponentMapping::default(),
+        }
+    }
+}
+
+impl ImageViewDesc {
+    /// Resolve the subresources of `image` covered by the view.
+    fn subresource_range(&self, image: &ImageDesc) -> Result<ImageSubresourceRange> {
+        let count = |base: u32, count: u32, remaining: u32, total: u32, name: &str| {
+            let count = if count == remaining {
+                total.saturating_sub(base)
+            } else {
+                count
+            };
+            if count == 0 || base.checked_add(count).is_none_or(|end| end > total) {
+                return Err(Error::new(
+                    ErrorKind::InvalidValue,
+                    "Image::create_image_view_with_desc",
                 )
-                .unwrap()
+                .with_context(format!(
+                    "{} {}..{} of an image with {}",
+                    name,
+                    base,
+                    base.saturating_add(count),
+                    total
+                )));
+            }
+            Ok(count)
+        };
+        let level_count = count(
+            self.base_mip_level,
+            self.level_count,
+            REMAINING_MIP_LEVELS,
+            image.mip_levels,
+            "mip levels",
+        )?;
+        let layer_count = count(
+            self.base_array_layer,
+            self.layer_count,
+            REMAINING_ARRAY_LAYERS,
+            image.array_layers,
+            "layers",
+        )?;
+        Ok(ImageSubresourceRange {
+            aspect_mask: self
+                .aspect
+                .unwrap_or_else(|| format_aspect(self.format.unwrap_or(image.format))),
+            base_mip_level: self.base_mip_level,
+            level_count,
+            base_array_layer: self.base_array_layer,
+            layer_count,
+        })
+    }
+}
+
+fn default_view_type(image_type: ImageType, layer_count: u32) -> ImageViewType {
+    match (image_type, layer_count > 1) {
+        (ImageType::TYPE_1D, false) => ImageViewType::TYPE_1D,
+        (ImageType::TYPE_1D, true) => ImageViewType::TYPE_1D_ARRAY,
+        (ImageType::TYPE_3D, _) => ImageViewType::TYPE_3D,
+        (_, false) => ImageViewType::TYPE_2D,
+        (_, true) => ImageViewType::TYPE_2D_ARRAY,
+    }
+}
+
+/// Aspects of the texels of `format`.
+pub(crate) fn format_aspect(format: Format) -> ImageAspectFlags {
+    match format {
+        Format::D16_UNORM | Format::X8_D24_UNORM_PACK32 | Format::D32_SFLOAT => {
+            ImageAspectFlags::DEPTH
+        }
+        Format::D16_UNORM_S8_UINT | Format::D24_UNORM_S8_UINT | Format::D32_SFLOAT_S8_UINT => {
+            ImageAspectFlags::DEPTH | ImageAspectFlags::STENCIL
         }
+        Format::S8_UINT => ImageAspectFlags::STENCIL,
+        _ => ImageAspectFlags::COLOR,
+    }
+}
+
+/// Mip level 0 and array layer 0 of a color image.
+pub(crate) fn color_subresource_range() -> ImageSubresourceRange {
+    ImageSubresourceRange::builder()
+        .aspect_mask(ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build()
+}
+
+/// Barrier that moves a color image from `old_layout` to `new_layout`.
+pub(crate) fn layout_barrier(
+    image: ash::vk::Image,
+    old_layout: ImageLayout,
+    new_layout: ImageLayout,
+    src_access_mask: AccessFlags,
+    dst_access_mask: AccessFlags,
+) -> ImageMemoryBarrier {
+    ImageMemoryBarrier::builder()
+        .src_access_mask(src_access_mask)
+        .dst_access_mask(dst_access_mask)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(color_subresource_range())
+        .build()
+}
+
+/// Copy of a whole color image to or from tightly packed rows of a buffer.
+pub(crate) fn color_copy_region(width: u32, height: u32) -> BufferImageCopy {
+    BufferImageCopy::builder()
+        .buffer_offset(0)
+        .buffer_row_length(0)
+        .buffer_image_height(0)
+        .image_subresource(
+            ImageSubresourceLayers::builder()
+                .aspect_mask(ImageAspectFlags::COLOR)
+                .mip_level(0)
+                .base_array_layer(0)
+                .layer_count(1)
+                .build(),
+        )
+        .image_extent(Extent3D {
+            width,
+            height,
+            depth: 1,
+        })
+        .build()
+}
+
+/// Size in bytes of a texel of `format`, or None for formats that are not supported for uploads,
+/// e.g. compressed or depth formats.
+pub(crate) fn texel_size(format: Format) -> Option<u64> {
+    match format {
+        Format::R8_UNORM | Format::R8_SRGB => Some(1),
+        Format::R8G8_UNORM | Format::R8G8_SRGB | Format::R16_SFLOAT => Some(2),
+        Format::R8G8B8A8_UNORM
+        | Format::R8G8B8A8_SRGB
+        | Format::B8G8R8A8_UNORM
+        | Format::B8G8R8A8_SRGB
+        | Format::A2B10G10R10_UNORM_PACK32
+        | Format::R16G16_SFLOAT
+        | Format::R32_SFLOAT => Some(4),
+        Format::R16G16B16A16_SFLOAT | Format::R32G32_SFLOAT => Some(8),
+        Format::R32G32B32A32_SFLOAT => Some(16),
+        _ => None,
     }
 }
 
 pub struct ImageView {
     pub(crate) inner: ash::vk::ImageView,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for ImageView {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_image_view(self.inner, None);
+        }
+    }
 }
 
 impl ImageView {
+    /// Create a frame buffer with this image view as its only attachment.
     pub fn create_frame_buffer(
         &self,
         device: &Device,
         render_pass: &RenderPass,
         width: u32,
         height: u32,
-    ) -> Result<FrameBuffer, GMResult> {
-        let create_info = FramebufferCreateInfo::builder()
-            .width(width)
-            .height(height)
-            .layers(1)
-            .render_pass(render_pass.inner)
-            .attachments(&[self.inner])
-            .build();
-        let inner = match unsafe { device.inner.create_framebuffer(&create_info, None) } {
-            Ok(f) => f,
-            Err(_) => return Err(GMResult::UnknownError),
-        };
-        Ok(FrameBuffer { inner })
+    ) -> Result<FrameBuffer> {
+        render_pass.create_frame_buffer(device, &[self], width, height)
     }
 }
 
-
 pub struct FrameBuffer {
     pub(crate) inner: ash::vk::Framebuffer,
+    pub(crate) device: Arc<RawDevice>,
 }
 
-pub struct SubPass(pub(crate) SubpassDescription);
+impl Drop for FrameBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_framebuffer(self.inner, None);
+        }
+    }
+}
+
+/// Description of an attachment of a render pass
+///
+/// The default is a single-sampled `R8G8B8A8_UNORM` color attachment
+/// that is stored and left in the `GENERAL` layout.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{AttachmentDesc, AttachmentLoadOp, ImageLayout};
+/// # fn example(swapchain: &gallium::Swapchain) {
+///
+/// // Color attachment of a swapchain image
+/// let attachment = AttachmentDesc {
+///     format: swapchain.format(),
+///     load_op: AttachmentLoadOp::CLEAR,
+///     final_layout: ImageLayout::PRESENT_SRC_KHR,
+///     ..Default::default()
+/// };
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct AttachmentDesc {
+    pub format: Format,
+    pub samples: SampleCountFlags,
+    pub load_op: AttachmentLoadOp,
+    pub store_op: AttachmentStoreOp,
+    pub stencil_load_op: AttachmentLoadOp,
+    pub stencil_store_op: AttachmentStoreOp,
+    pub initial_layout: ImageLayout,
+    pub final_layout: ImageLayout,
+}
+
+impl Default for AttachmentDesc {
+    fn default() -> Self {
+        Self {
+            format: Format::R8G8B8A8_UNORM,
+            samples: SampleCountFlags::TYPE_1,
+            load_op: AttachmentLoadOp::DONT_CARE,
+            store_op: AttachmentStoreOp::STORE,
+            stencil_load_op: AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: AttachmentStoreOp::DONT_CARE,
+            initial_layout: ImageLayout::UNDEFINED,
+            final_layout: ImageLayout::GENERAL,
+        }
+    }
+}
+
+impl AttachmentDesc {
+    pub(crate) fn to_vk(self) -> AttachmentDescription {
+        AttachmentDescription::builder()
+            .format(self.format)
+            .samples(self.samples)
+            .load_op(self.load_op)
+            .store_op(self.store_op)
+            .stencil_load_op(self.stencil_load_op)
+            .stencil_store_op(self.stencil_store_op)
+            .initial_layout(self.initial_layout)
+            .final_layout(self.final_layout)
+            .build()
+    }
+}
+
+/// Represents a subpass of a render pass
+///
+/// It refers to attachments of [RenderPassDesc] by index.
+#[derive(Clone, Debug, Default)]
+pub struct SubPass {
+    pub(crate) color_attachments: Vec<AttachmentReference>,
+    pub(crate) depth_stencil_attachment: Option<AttachmentReference>,
+}
 
 impl SubPass {
+    /// Subpass that renders to the color attachment 0.
     pub fn new() -> Self {
-        let subpass_attachment = vec![AttachmentReference::builder()
-            .attachment(0)
-            .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-            .build()];
-        let subpass = SubpassDescription::builder()
+        Self::with_attachments(&[0], None)
+    }
+
+    /// Subpass that renders to the given attachments.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - Indices of the color attachments.
+    /// * `depth_stencil` - Index of the depth/stencil attachment.
+    pub fn with_attachments(color: &[u32], depth_stencil: Option<u32>) -> Self {
+        let color_attachments = color
+            .iter()
+            .map(|i| {
+                AttachmentReference::builder()
+                    .attachment(*i)
+                    .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .build()
+            })
+            .collect();
+        let depth_stencil_attachment = depth_stencil.map(|i| {
+            AttachmentReference::builder()
+                .attachment(i)
+                .layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .build()
+        });
+        Self {
+            color_attachments,
+            depth_stencil_attachment,
+        }
+    }
+
+    pub(crate) fn to_vk(&self) -> SubpassDescription {
+        let mut subpass = SubpassDescription::builder()
             .pipeline_bind_point(PipelineBindPoint::GRAPHICS)
-            .color_attachments(&subpass_attachment)
-            .build();
-        Self { 0: subpass }
+            .color_attachments(&self.color_attachments);
+        if let Some(depth_stencil) = &self.depth_stencil_attachment {
+            subpass = subpass.depth_stencil_attachment(depth_stencil);
+        }
+        subpass.build()
     }
 }
 
-impl Default for SubPass {
+/// Description for Render Pass Creation
+///
+/// The default has one [AttachmentDesc::default] attachment and one [SubPass::new] subpass.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{AttachmentDesc, AttachmentLoadOp, RenderPassDesc, SubPass};
+/// # fn example(device: &gallium::Device) {
+///
+/// let desc = RenderPassDesc {
+///     attachments: vec![AttachmentDesc {
+///         load_op: AttachmentLoadOp::CLEAR,
+///         ..Default::default()
+///     }],
+///     subpasses: vec![SubPass::new()],
+/// };
+/// let render_pass = device.create_render_pass(&desc).unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RenderPassDesc {
+    pub attachments: Vec<AttachmentDesc>,
+    pub subpasses: Vec<SubPass>,
+}
+
+impl Default for RenderPassDesc {
     fn default() -> Self {
-        Self(Default::default())
+        Self {
+            attachments: vec![AttachmentDesc::default()],
+            subpasses: vec![SubPass::new()],
+        }
+    }
+}
+
+/// Value used to clear an attachment at the beginning of a render pass
+///
+/// One value is given per attachment, in the order of [RenderPassDesc::attachments].
+/// Attachments that are not loaded with `AttachmentLoadOp::CLEAR` ignore it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClearValue {
+    Color([f32; 4]),
+    DepthStencil { depth: f32, stencil: u32 },
+}
+
+impl From<ClearValue> for ash::vk::ClearValue {
+    fn from(value: ClearValue) -> Self {
+        match value {
+            ClearValue::Color(float32) => ash::vk::ClearValue {
+                color: ClearColorValue { float32 },
+            },
+            ClearValue::DepthStencil { depth, stencil } => ash::vk::ClearValue {
+                depth_stencil: ClearDepthStencilValue { depth, stencil },
+            },
+        }
     }
 }
 
 pub struct RenderPass {
     pub(crate) inner: ash::vk::RenderPass,
+    /// Number of color attachments of each subpass.
+    pub(crate) color_attachment_counts: Vec<usize>,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for RenderPass {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_render_pass(self.inner, None);
+        }
+    }
 }
 
 impl RenderPass {
-    pub fn create_pipeline(
+    /// Create a frame buffer with several attachments.
+    ///
+    /// # Arguments
+    ///
+    /// * `attachments` - Image views in the order of [RenderPassDesc::attachments].
+    pub fn create_frame_buffer(
         &self,
-        image: &Image,
         device: &Device,
-        shaders: &[Shader],
-    ) -> Result<Vec<Pipeline>, GMResult> {
-        if shaders.is_empty() {
-            return Err(GMResult::InvalidValue);
-        }
-        let mut shader_stages = vec![];
-        let entry = CString::new("main").unwrap();
-        for i in shaders {
-            let flag = match i.kind {
-                crate::ShaderKind::Vertex => ShaderStageFlags::VERTEX,
-                crate::ShaderKind::Fragment => ShaderStageFlags::FRAGMENT,
-            };
-            shader_stages.push(
-                PipelineShaderStageCreateInfo::builder()
-                    .module(i.inner)
-                    .name(entry.as_c_str())
-                    .stage(flag)
-                    .build(),
-            );
-        }
-        let viewport_state_info = PipelineViewportStateCreateInfo::builder()
-            .viewports(&[image.viewport])
-            .scissors(&image.scissors)
-            .build();
-        let vertex_input_info = PipelineVertexInputStateCreateInfo::builder()
-            .vertex_attribute_descriptions(&[])
-            .vertex_binding_descriptions(&[])
-            .build();
-        let input_assembly = PipelineInputAssemblyStateCreateInfo::builder()
-            .topology(PrimitiveTopology::TRIANGLE_LIST)
-            .primitive_restart_enable(false)
-            .build();
-        let rasterizer = PipelineRasterizationStateCreateInfo::builder()
-            .depth_clamp_enable(false)
-            .rasterizer_discard_enable(false)
-            .polygon_mode(PolygonMode::FILL)
-            .line_width(1.0)
-            .cull_mode(CullModeFlags::BACK)
-            .front_face(FrontFace::CLOCKWISE)
-            .depth_bias_enable(false)
-            .build();
-        let multisample = PipelineMultisampleStateCreateInfo::builder()
-            .sample_shading_enable(false)
-            .rasterization_samples(SampleCountFlags::TYPE_1)
-            .build();
-        let blend_attachment = vec![PipelineColorBlendAttachmentState::builder()
-            .color_write_mask(
-                ColorComponentFlags::A
-                    | ColorComponentFlags::R
-                    | ColorComponentFlags::G
-                    | ColorComponentFlags::B,
-            )
-            .blend_enable(false)
-            .build()];
-        let blend = PipelineColorBlendStateCreateInfo::builder()
-            .logic_op_enable(false)
-            .attachments(&blend_attachment)
-            .build();
-        let layout_create_info = PipelineLayoutCreateInfo::builder().set_layouts(&[]).build();
-
-        let pipeline_layout = match unsafe {
-            device
-                .inner
-                .create_pipeline_layout(&layout_create_info, None)
-        } {
-            Ok(p) => p,
-            Err(e) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_INITIALIZATION_FAILED => {
-                        return Err(GMResult::InitializationError)
-                    }
-                    crate::vk::VK_ERROR_INCOMPATIBLE_DRIVER => {
-                        return Err(GMResult::IncompatibleDriver)
-                    }
-                    _ => return Err(GMResult::UnknownError),
-                }
-            }
-        };
-
-        let pipeline_create_info = GraphicsPipelineCreateInfo::builder()
-            .viewport_state(&viewport_state_info)
-            .vertex_input_state(&vertex_input_info)
-            .input_assembly_state(&input_assembly)
-            .rasterization_state(&rasterizer)
-            .multisample_state(&multisample)
-            .color_blend_state(&blend)
-            .layout(pipeline_layout)
-            .stages(&[])
+        attachments: &[&ImageView],
+        width: u32,
+        height: u32,
+    ) -> Result<FrameBuffer> {
+        let attachments: Vec<_> = attachments.iter().map(|a| a.inner).collect();
+        let create_info = FramebufferCreateInfo::builder()
+            .width(width)
+            .height(height)
+            .layers(1)
             .render_pass(self.inner)
-            .subpass(0)
-            .stages(&shader_stages)
+            .attachments(&attachments)
             .build();
-
-        let pipeline = match unsafe {
-            device.inner.create_graphics_pipelines(
-                PipelineCache::null(),
-                &[pipeline_create_info],
-                None,
-            )
-        } {
-            Ok(p) => p,
-            Err((p, e)) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_INITIALIZATION_FAILED => {
-                        return Err(GMResult::InitializationError)
-                    }
-                    crate::vk::VK_ERROR_INCOMPATIBLE_DRIVER => {
-                        return Err(GMResult::IncompatibleDriver)
-                    }
-                    _ => return Err(GMResult::UnknownError),
-                }
-            }
-        };
-
-        let mut pipelines = vec![];
-
-        for i in pipeline {
-            pipelines.push(Pipeline { inner: i });
-        }
-
-        Ok(pipelines)
+        let inner = unsafe { device.inner.create_framebuffer(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateFramebuffer"))?;
+        Ok(FrameBuffer {
+            inner,
+            device: device.inner.clone(),
+        })
     }
 }
@@ -1,19 +1,34 @@
-use std::ffi::CString;
+use std::{
+    ffi::{c_char, CStr, CString},
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
 
 use ash::{
-    vk::{ApplicationInfo, DeviceCreateInfo, DeviceQueueCreateInfo, InstanceCreateInfo},
+    extensions::ext::DebugUtils,
+    vk::{
+        api_version_major, api_version_minor, ApplicationInfo, DeviceCreateInfo,
+        DeviceQueueCreateInfo, InstanceCreateInfo, PhysicalDeviceFeatures, PhysicalDeviceFeatures2,
+        PhysicalDeviceVulkan12Features, API_VERSION_1_0, API_VERSION_1_2,
+    },
     Entry,
 };
+#[cfg(feature = "surface")]
 use raw_window_handle::HasRawDisplayHandle;
 
-use crate::{Device, GMResult, GPUQueueInfo, GPU};
+use crate::{
+    Allocator, DebugCallback, DebugMessenger, Device, Error, ErrorKind, GPUQueueInfo, RawDevice,
+    Result, ValidationDesc, GPU, VALIDATION_LAYER_NAME,
+};
 
 /// Description for Instance Creation
 ///  
 /// Instance::new() to create an instance
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct InstanceDesc {
     pub app_name: String,
+    /// Enables the validation layer when set. See [ValidationDesc].
+    pub validation: Option<ValidationDesc>,
 }
 
 /// Represents a physical device  
@@ -27,6 +42,7 @@ pub struct InstanceDesc {
 /// fn main() {
 ///     let instance = match Instance::new(InstanceDesc {
 ///         app_name: "Example".to_owned(),
+///         ..Default::default()
 ///     }) {
 ///         Ok(i) => i,
 ///         Err(e) => panic!("{:?}",e),
@@ -34,8 +50,44 @@ pub struct InstanceDesc {
 /// }
 /// ```
 pub struct Instance {
+    #[cfg_attr(not(feature = "surface"), allow(dead_code))]
     pub(crate) entry: Entry,
-    pub(crate) instance: ash::Instance,
+    pub(crate) instance: Arc<RawInstance>,
+    pub(crate) validation_enabled: bool,
+    /// Extensions enabled on the instance.
+    #[cfg_attr(not(feature = "surface"), allow(dead_code))]
+    pub(crate) extensions: Vec<CString>,
+}
+
+/// Owns the `VkInstance`
+///
+/// Objects created from the instance hold an `Arc` of it so that it is destroyed last.
+pub(crate) struct RawInstance {
+    pub(crate) inner: ash::Instance,
+    /// Vulkan version requested for the instance, at most 1.2.
+    pub(crate) api_version: u32,
+    pub(crate) debug_messenger: Option<DebugMessenger>,
+    // Referenced by the debug messenger through `p_user_data` until the instance is destroyed.
+    pub(crate) _debug_callback: Box<Option<DebugCallback>>,
+}
+
+impl Deref for RawInstance {
+    type Target = ash::Instance;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl Drop for RawInstance {
+    fn drop(&mut self) {
+        if let Some(debug_messenger) = &self.debug_messenger {
+            debug_messenger.destroy();
+        }
+        unsafe {
+            self.inner.destroy_instance(None);
+        }
+    }
 }
 
 impl Instance {
@@ -48,105 +100,209 @@ impl Instance {
     /// use gallium::{Instance, InstanceDesc};
     ///
     /// fn main() {
-    ///     let desc = InstanceDesc { app_name: "example".to_owned() };
+    ///     let desc = InstanceDesc { app_name: "example".to_owned(), ..Default::default() };
     ///     let instance = Instance::new(desc).unwrap();
     /// }
     /// ```
-    pub fn new(desc: InstanceDesc) -> Result<Self, GMResult> {
-        let entry = ash::Entry::linked();
-        let app_info = ApplicationInfo::builder()
-            .api_version(ash::vk::API_VERSION_1_0)
-            .application_name(CString::new(desc.app_name).unwrap().as_c_str())
-            .build();
-
-        let create_info = InstanceCreateInfo::builder()
-            .application_info(&app_info)
-            .build();
-        let instance = match unsafe { entry.create_instance(&create_info, None) } {
-            Ok(i) => i,
-            Err(e) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_INITIALIZATION_FAILED => {
-                        return Err(GMResult::InitializationError)
-                    }
-                    crate::vk::VK_ERROR_INCOMPATIBLE_DRIVER => {
-                        return Err(GMResult::IncompatibleDriver)
-                    }
-                    _ => return Err(GMResult::UnknownError),
-                }
-            }
-        };
-        Ok(Self { entry, instance })
+    pub fn new(desc: InstanceDesc) -> Result<Self> {
+        Self::create(desc, vec![])
     }
 
     #[cfg(feature = "surface")]
-    pub fn new_with_surface(
-        window: &impl HasRawDisplayHandle,
-        desc: InstanceDesc,
-    ) -> Result<Self, GMResult> {
+    pub fn new_with_surface(window: &impl HasRawDisplayHandle, desc: InstanceDesc) -> Result<Self> {
+        let extension_names =
+            ash_window::enumerate_required_extensions(window.raw_display_handle())
+                .map_err(|e| Error::from_vk(e, "ash_window::enumerate_required_extensions"))?
+                .to_vec();
+        Self::create(desc, extension_names)
+    }
+
+    /// Create an instance for headless surfaces
+    ///
+    /// Enables `VK_EXT_headless_surface` for `Surface::create_headless`.
+    ///
+    /// * `desc` - Description for Instance Creation.
+    #[cfg(feature = "headless_surface")]
+    pub fn new_headless(desc: InstanceDesc) -> Result<Self> {
+        Self::create(
+            desc,
+            vec![
+                ash::extensions::khr::Surface::name().as_ptr(),
+                ash::extensions::ext::HeadlessSurface::name().as_ptr(),
+            ],
+        )
+    }
+
+    fn create(desc: InstanceDesc, mut extension_names: Vec<*const c_char>) -> Result<Self> {
         let entry = ash::Entry::linked();
+        let app_name = CString::new(desc.app_name).map_err(|e| {
+            Error::new(ErrorKind::InvalidValue, "Instance::new").with_context(e.to_string())
+        })?;
+        // Timeline semaphores are core in Vulkan 1.2. Older loaders still get an instance,
+        // and devices created from it go without them.
+        let loader_version = entry
+            .try_enumerate_instance_version()
+            .map_err(|e| Error::from_vk(e, "vkEnumerateInstanceVersion"))?
+            .unwrap_or(API_VERSION_1_0);
+        let api_version = loader_version.min(API_VERSION_1_2);
+        if api_version < API_VERSION_1_2 {
+            log::info!(
+                "the Vulkan loader supports {}.{}, timeline semaphores are unavailable",
+                api_version_major(loader_version),
+                api_version_minor(loader_version)
+            );
+        }
         let app_info = ApplicationInfo::builder()
-            .api_version(ash::vk::API_VERSION_1_0)
-            .application_name(CString::new(desc.app_name).unwrap().as_c_str())
+            .api_version(api_version)
+            .application_name(app_name.as_c_str())
             .build();
 
-        let mut extension_names =
-            ash_window::enumerate_required_extensions(window.raw_display_handle())
-                .unwrap()
-                .to_vec();
+        let mut layer_names = vec![];
+        let mut validation = None;
+        if let Some(validation_desc) = desc.validation {
+            if Self::is_layer_present(&entry, VALIDATION_LAYER_NAME) {
+                layer_names.push(VALIDATION_LAYER_NAME.as_ptr());
+                if Self::is_extension_present(&entry, DebugUtils::name()) {
+                    extension_names.push(DebugUtils::name().as_ptr());
+                    validation = Some(validation_desc);
+                } else {
+                    log::warn!(
+                        "{} is not present, validation messages will not be reported",
+                        DebugUtils::name().to_string_lossy()
+                    );
+                }
+            } else {
+                log::warn!(
+                    "{} is not installed, validation is disabled",
+                    VALIDATION_LAYER_NAME.to_string_lossy()
+                );
+            }
+        }
+        let validation_enabled = !layer_names.is_empty();
+        let extensions = extension_names
+            .iter()
+            .map(|name| unsafe { CStr::from_ptr(*name) }.to_owned())
+            .collect();
 
-        let create_info = InstanceCreateInfo::builder()
+        // The callback is boxed so that its address stays valid for the messenger.
+        let callback = Box::new(validation.as_mut().and_then(|v| v.callback.take()));
+        let mut messenger_info = validation
+            .as_ref()
+            .map(|v| DebugMessenger::create_info(v, &callback));
+
+        let mut create_info = InstanceCreateInfo::builder()
             .application_info(&app_info)
-            .enabled_extension_names(&extension_names)
-            .build();
-        let instance = match unsafe { entry.create_instance(&create_info, None) } {
-            Ok(i) => i,
-            Err(e) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_INITIALIZATION_FAILED => {
-                        return Err(GMResult::InitializationError)
-                    }
-                    crate::vk::VK_ERROR_INCOMPATIBLE_DRIVER => {
-                        return Err(GMResult::IncompatibleDriver)
-                    }
-                    crate::vk::VK_ERROR_EXTENSION_NOT_PRESENT => {
-                        return Err(GMResult::VkExtensionNotPresent)
+            .enabled_layer_names(&layer_names)
+            .enabled_extension_names(&extension_names);
+        if let Some(messenger_info) = messenger_info.as_mut() {
+            // Also reports messages from vkCreateInstance and vkDestroyInstance.
+            create_info = create_info.push_next(messenger_info);
+        }
+        let create_info = create_info.build();
+        let instance = unsafe { entry.create_instance(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateInstance"))?;
+
+        let debug_messenger = match messenger_info {
+            Some(mut messenger_info) => {
+                messenger_info.p_next = std::ptr::null();
+                let loader = DebugUtils::new(&entry, &instance);
+                match unsafe { loader.create_debug_utils_messenger(&messenger_info, None) } {
+                    Ok(messenger) => Some(DebugMessenger { loader, messenger }),
+                    Err(_) => {
+                        log::warn!("Failed to create the debug messenger");
+                        None
                     }
-                    _ => return Err(GMResult::UnknownError),
                 }
             }
+            None => None,
         };
-        Ok(Self { entry, instance })
+
+        Ok(Self {
+            entry,
+            instance: Arc::new(RawInstance {
+                inner: instance,
+                api_version,
+                debug_messenger,
+                _debug_callback: callback,
+            }),
+            validation_enabled,
+            extensions,
+        })
+    }
+
+    fn is_layer_present(entry: &Entry, name: &CStr) -> bool {
+        match entry.enumerate_instance_layer_properties() {
+            Ok(layers) => layers
+                .iter()
+                .any(|l| unsafe { CStr::from_ptr(l.layer_name.as_ptr()) } == name),
+            Err(_) => false,
+        }
+    }
+
+    fn is_extension_present(entry: &Entry, name: &CStr) -> bool {
+        let is_present =
+            |layer: Option<&CStr>| match entry.enumerate_instance_extension_properties(layer) {
+                Ok(extensions) => extensions
+                    .iter()
+                    .any(|e| unsafe { CStr::from_ptr(e.extension_name.as_ptr()) } == name),
+                Err(_) => false,
+            };
+        is_present(None) || is_present(Some(VALIDATION_LAYER_NAME))
+    }
+
+    /// Return an error of kind `ErrorKind::ExtensionNotPresent` unless all of `names` are enabled
+    /// on this instance.
+    ///
+    /// Extension functions of ash panic when their extension is missing, so check before loading
+    /// them.
+    #[cfg(feature = "surface")]
+    pub(crate) fn require_extensions(
+        &self,
+        names: &[&CStr],
+        operation: &'static str,
+    ) -> Result<()> {
+        match names
+            .iter()
+            .find(|name| !self.extensions.iter().any(|e| e.as_c_str() == **name))
+        {
+            Some(name) => Err(
+                Error::new(ErrorKind::ExtensionNotPresent, operation).with_context(format!(
+                    "{} is not enabled on the instance",
+                    name.to_string_lossy()
+                )),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns true if `VK_LAYER_KHRONOS_validation` is enabled on this instance.
+    ///
+    /// It is false when validation was not requested or the layer is not installed.
+    pub fn is_validation_enabled(&self) -> bool {
+        self.validation_enabled
     }
 
     /// Get a list of available physical devices (GPUs).  
     /// GPUs are required to create devices  
     ///
     /// # Example
-    /// ```
-    /// use gallium::Instance;
+    /// ```no_run
+    /// use gallium::{Instance, InstanceDesc};
     ///
     /// fn main() {
-    ///     let instance = match Instance::new(InstanceDesc {
+    ///     let instance = Instance::new(InstanceDesc {
     ///         app_name: "Example".to_owned(),
+    ///         ..Default::default()
     ///     }).unwrap();
-    ///     let gpu = instance.enumerate_gpu();
+    ///     let gpu = instance.enumerate_gpu().unwrap();
     ///     for i in gpu {
     ///         println!("{}",i.name());
     ///     }
     /// }
     /// ```
-    pub fn enumerate_gpu(&self) -> Result<Vec<GPU>, GMResult> {
-        let devices = match unsafe { self.instance.enumerate_physical_devices() } {
-            Ok(d) => d,
-            Err(e) => return Err(GMResult::UnknownError),
-        };
+    pub fn enumerate_gpu(&self) -> Result<Vec<GPU>> {
+        let devices = unsafe { self.instance.enumerate_physical_devices() }
+            .map_err(|e| Error::from_vk(e, "vkEnumeratePhysicalDevices"))?;
         let mut gpu = vec![];
         for i in devices {
             let device_property = unsafe { self.instance.get_physical_device_properties(i) };
@@ -168,56 +324,136 @@ impl Instance {
     /// * `info` - GPU Queue Information
     ///
     /// # Example
-    /// ```
+    /// ```no_run
+    /// use gallium::{GPUQueueInfo, Instance};
+    /// # fn example(instance: &Instance) {
+    ///
     /// let v_gpu = instance.enumerate_gpu().unwrap();
-    /// let mut index = 0;
     /// let mut gpu_index = 0;
     /// let mut info = GPUQueueInfo::default();
     /// for (i, g) in v_gpu.iter().enumerate() {
-    ///     if g.is_support_graphics(&instance, &mut info) {
+    ///     if g.is_support_graphics(instance, &mut info) {
     ///         println!("Supported! Name: {}", g.name());
     ///         gpu_index = i;
     ///     }
     /// }
     /// let gpu = &v_gpu[gpu_index];
     /// let device = instance.create_device(gpu, info).unwrap();
+    /// # }
     /// ```
-    pub fn create_device(&self, gpu: &GPU, info: GPUQueueInfo) -> Result<Device, GMResult> {
-        let mut queue_create_info = DeviceQueueCreateInfo::builder()
-            .queue_family_index(info.index)
-            .queue_priorities(&[1.0])
-            .build();
-        queue_create_info.queue_count = info.count;
-        let mut enabled_extension_names = vec![];
-        let swapchain = CString::new("VK_KHR_swapchain").unwrap();
-        let swapchain_cstr = swapchain.as_c_str();
-        enabled_extension_names.push(swapchain_cstr.as_ptr());
-        let create_info = DeviceCreateInfo::builder()
-            .queue_create_infos(&[queue_create_info])
-            .enabled_extension_names(&enabled_extension_names)
-            .build();
-        let device = match unsafe { self.instance.create_device(gpu.device, &create_info, None) } {
-            Ok(d) => d,
-            Err(e) => {
-                let code = e.as_raw();
-                match code {
-                    crate::vk::VK_ERROR_OUT_OF_HOST_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_OUT_OF_DEVICE_MEMORY => return Err(GMResult::OutOfMemory),
-                    crate::vk::VK_ERROR_INITIALIZATION_FAILED => {
-                        return Err(GMResult::InitializationError)
-                    }
-                    _ => return Err(GMResult::UnknownError),
-                }
-            }
-        };
-        Ok(Device { inner: device })
+    pub fn create_device(&self, gpu: &GPU, info: GPUQueueInfo) -> Result<Device> {
+        self.create_device_with_queues(gpu, &[info])
     }
-}
 
-impl Drop for Instance {
-    fn drop(&mut self) {
-        unsafe {
-            self.instance.destroy_instance(None);
+    /// Create a device with queues of several families
+    ///
+    /// Queues of each family can then be obtained with `Device::get_queue`.
+    ///
+    /// # Arguments
+    ///
+    /// * `gpu` - A suitable GPU for creating the device.
+    /// * `infos` - GPU Queue Information of each family, e.g. from `GPU::is_support_graphics` and `GPU::is_support_compute`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gallium::{GPUQueueInfo, GPU, Instance};
+    /// # fn example(instance: &Instance, gpu: &GPU) {
+    ///
+    /// let mut graphics = GPUQueueInfo::default();
+    /// let mut compute = GPUQueueInfo::default();
+    /// if gpu.is_support_graphics(instance, &mut graphics)
+    ///     && gpu.is_support_compute(instance, &mut compute)
+    /// {
+    ///     let device = instance
+    ///         .create_device_with_queues(gpu, &[graphics, compute])
+    ///         .unwrap();
+    ///     let queue = device.get_queue(graphics).unwrap();
+    ///     let compute_queue = device.get_queue(compute).unwrap();
+    /// }
+    /// # }
+    /// ```
+    pub fn create_device_with_queues(&self, gpu: &GPU, infos: &[GPUQueueInfo]) -> Result<Device> {
+        if infos.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidValue,
+                "Instance::create_device_with_queues",
+            )
+            .with_context("no queue families given"));
         }
+        // A family may only be created once; the same family can come from several queries.
+        let mut queue_families: Vec<GPUQueueInfo> = vec![];
+        for info in infos {
+            if !queue_families.iter().any(|f| f.index == info.index) {
+                queue_families.push(*info);
+            }
+        }
+        let queue_priorities: Vec<Vec<f32>> = queue_families
+            .iter()
+            .map(|info| vec![1.0; info.count.max(1) as usize])
+            .collect();
+        let queue_create_infos: Vec<_> = queue_families
+            .iter()
+            .zip(&queue_priorities)
+            .map(|(info, priorities)| {
+                DeviceQueueCreateInfo::builder()
+                    .queue_family_index(info.index)
+                    .queue_priorities(priorities)
+                    .build()
+            })
+            .collect();
+        let enabled_extension_names = vec![ash::extensions::khr::Swapchain::name().as_ptr()];
+
+        // Both the instance and the GPU must support 1.2 to query and enable its features.
+        let vulkan_12 = self.instance.api_version >= API_VERSION_1_2
+            && gpu.device_property.api_version >= API_VERSION_1_2;
+        let mut supported_12 = PhysicalDeviceVulkan12Features::default();
+        if vulkan_12 {
+            let mut features = PhysicalDeviceFeatures2::builder().push_next(&mut supported_12);
+            unsafe {
+                self.instance
+                    .get_physical_device_features2(gpu.device, &mut features)
+            };
+        }
+        let timeline_semaphore = supported_12.timeline_semaphore == ash::vk::TRUE;
+        let mut enabled_12 =
+            PhysicalDeviceVulkan12Features::builder().timeline_semaphore(timeline_semaphore);
+        // Optional features of `GraphicsPipelineDesc` and `SamplerDesc`, enabled when the GPU
+        // supports them.
+        let supported = unsafe { self.instance.get_physical_device_features(gpu.device) };
+        let features = PhysicalDeviceFeatures::builder()
+            .fill_mode_non_solid(supported.fill_mode_non_solid == ash::vk::TRUE)
+            .wide_lines(supported.wide_lines == ash::vk::TRUE)
+            .logic_op(supported.logic_op == ash::vk::TRUE)
+            .sampler_anisotropy(supported.sampler_anisotropy == ash::vk::TRUE)
+            .build();
+        let mut create_info = DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_create_infos)
+            .enabled_extension_names(&enabled_extension_names)
+            .enabled_features(&features);
+        if vulkan_12 {
+            create_info = create_info.push_next(&mut enabled_12);
+        }
+        let create_info = create_info.build();
+        let device = unsafe { self.instance.create_device(gpu.device, &create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateDevice").with_context(gpu.name()))?;
+        let memory_properties = unsafe {
+            self.instance
+                .get_physical_device_memory_properties(gpu.device)
+        };
+        Ok(Device {
+            inner: Arc::new(RawDevice {
+                inner: device,
+                gpu: gpu.device,
+                properties: gpu.device_property,
+                timeline_semaphore,
+                features,
+                queue_families: queue_families.iter().map(|f| f.index).collect(),
+                allocator: Mutex::new(Allocator::new(
+                    memory_properties,
+                    &gpu.device_property.limits,
+                )),
+                instance: self.instance.clone(),
+            }),
+        })
     }
 }
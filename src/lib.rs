@@ -1,12 +1,22 @@
+mod allocator;
+mod buffer;
+mod debug;
+mod descriptor;
 mod device;
+mod error;
 mod fence;
+mod frame;
 mod gallium;
 mod image;
 mod instance;
 mod mem;
+mod offscreen;
 mod pipeline;
 mod queue;
+mod sampler;
+mod semaphore;
 mod shader;
+mod texture;
 
 #[cfg(feature = "surface")]
 mod surface;
@@ -15,37 +25,26 @@ mod swapchain;
 #[doc(hidden)]
 mod vk;
 
+pub use allocator::*;
+pub use buffer::*;
+pub use debug::*;
+pub use descriptor::*;
 pub use device::*;
+pub use error::*;
 pub use fence::*;
+pub use frame::*;
 pub use gallium::*;
 pub use image::*;
 pub use instance::*;
 pub use mem::*;
+pub use offscreen::*;
 pub use pipeline::*;
 pub use queue::*;
+pub use sampler::*;
+pub use semaphore::*;
 pub use shader::*;
 
 #[cfg(feature = "surface")]
 pub use surface::*;
 #[cfg(feature = "surface")]
 pub use swapchain::*;
-
-/// Result value returned when gallium instruction is executed
-///
-/// # Value Meaning
-/// * `IncompatibleDriver` - Unsupported driver is used.
-/// * `InitializationError` - Initialization failed for some reason.
-/// * `InvalidValue` - Invalid value passed.
-/// * `OutOfMemory` - Out of memory.
-/// * `UnknownError` - Unknown error.
-#[derive(Clone, Copy, Debug)]
-pub enum GMResult {
-    Success,
-    IncompatibleDriver,
-    InitializationError,
-    InvalidValue,
-    OutOfMemory,
-    UnknownError,
-
-    VkExtensionNotPresent,
-}
@@ -1,9 +1,177 @@
-use ash::vk::DeviceMemory;
+use ash::vk::{MappedMemoryRange, MemoryPropertyFlags, PhysicalDeviceMemoryProperties};
+use bytemuck::Pod;
 
-use crate::{Device, Image};
+use crate::{Allocation, Error, ErrorKind, RawDevice, Result};
 
-pub struct MappedMemory {
-    memory: DeviceMemory,
+/// CPU access to the memory of a buffer or an image
+///
+/// It borrows the resource, so the resource cannot be dropped or mapped again while the guard
+/// is alive. Non-coherent memory is invalidated when the guard is created and flushed when it is
+/// dropped after a mutable access. The memory itself stays mapped by the device.
+///
+/// # Example
+/// ```no_run
+/// # fn example(image: &mut gallium::Image) {
+/// let memory = image.map_memory().unwrap();
+/// let pixels: &[[u8; 4]] = memory.as_slice().unwrap();
+/// println!("first pixel: {:?}", pixels[0]);
+/// # }
+/// ```
+pub struct MappedMemory<'a> {
+    ptr: *mut u8,
+    len: usize,
+    range: Option<MappedMemoryRange>,
+    written: bool,
+    device: &'a RawDevice,
 }
 
-impl MappedMemory {}
+impl<'a> MappedMemory<'a> {
+    /// Map the first `len` bytes of `allocation`.
+    pub(crate) fn new(
+        device: &'a RawDevice,
+        allocation: &Allocation,
+        len: u64,
+        operation: &'static str,
+    ) -> Result<Self> {
+        let ptr = allocation.mapped_ptr().ok_or_else(|| {
+            Error::new(ErrorKind::MemoryMapFailed, operation)
+                .with_context("the memory is not host visible")
+        })?;
+        let range = allocation.non_coherent_range();
+        if let Some(range) = range {
+            unsafe { device.invalidate_mapped_memory_ranges(&[range]) }
+                .map_err(|e| Error::from_vk(e, "vkInvalidateMappedMemoryRanges"))?;
+        }
+        Ok(Self {
+            ptr,
+            len: len as usize,
+            range,
+            written: false,
+            device,
+        })
+    }
+
+    /// Size of the mapped memory in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// The memory is flushed when the guard is dropped.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.written = true;
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+
+    /// View the memory as elements of `T`
+    ///
+    /// Returns an error of kind `ErrorKind::InvalidValue` if the size is not a multiple of the
+    /// size of `T`, or the memory is not aligned for `T`.
+    pub fn as_slice<T: Pod>(&self) -> Result<&[T]> {
+        bytemuck::try_cast_slice(self.as_bytes())
+            .map_err(|e| cast_error::<T>(e, "MappedMemory::as_slice"))
+    }
+
+    /// View the memory as mutable elements of `T`
+    ///
+    /// The memory is flushed when the guard is dropped.
+    /// Returns an error of kind `ErrorKind::InvalidValue` if the size is not a multiple of the
+    /// size of `T`, or the memory is not aligned for `T`.
+    pub fn as_slice_mut<T: Pod>(&mut self) -> Result<&mut [T]> {
+        bytemuck::try_cast_slice_mut(self.as_bytes_mut())
+            .map_err(|e| cast_error::<T>(e, "MappedMemory::as_slice_mut"))
+    }
+
+    /// Make the writes visible to the GPU now instead of when the guard is dropped.
+    pub fn flush(&mut self) -> Result<()> {
+        if let (Some(range), true) = (self.range, self.written) {
+            unsafe { self.device.flush_mapped_memory_ranges(&[range]) }
+                .map_err(|e| Error::from_vk(e, "vkFlushMappedMemoryRanges"))?;
+        }
+        self.written = false;
+        Ok(())
+    }
+}
+
+fn cast_error<T>(e: bytemuck::PodCastError, operation: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidValue, operation).with_context(format!(
+        "{:?} viewing memory as {}",
+        e,
+        std::any::type_name::<T>()
+    ))
+}
+
+impl Drop for MappedMemory<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::error!("failed to flush mapped memory: {}", e);
+        }
+    }
+}
+
+/// Indicates where the memory of a resource is placed
+///
+/// # Value Meaning
+/// * `GpuOnly` - Device-local memory that the CPU cannot access.
+/// * `CpuToGpu` - Host-visible memory written by the CPU and read by the GPU, e.g. vertex data.
+/// * `GpuToCpu` - Host-visible memory written by the GPU and read back by the CPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MemoryLocation {
+    #[default]
+    GpuOnly,
+    CpuToGpu,
+    GpuToCpu,
+}
+
+impl MemoryLocation {
+    /// Property flags the memory type must have.
+    pub(crate) fn required_flags(self) -> MemoryPropertyFlags {
+        match self {
+            MemoryLocation::GpuOnly => MemoryPropertyFlags::DEVICE_LOCAL,
+            MemoryLocation::CpuToGpu => {
+                MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT
+            }
+            MemoryLocation::GpuToCpu => MemoryPropertyFlags::HOST_VISIBLE,
+        }
+    }
+
+    /// Property flags that are used if a memory type has them.
+    pub(crate) fn preferred_flags(self) -> MemoryPropertyFlags {
+        match self {
+            MemoryLocation::GpuOnly => MemoryPropertyFlags::empty(),
+            MemoryLocation::CpuToGpu => MemoryPropertyFlags::DEVICE_LOCAL,
+            MemoryLocation::GpuToCpu => {
+                MemoryPropertyFlags::HOST_CACHED | MemoryPropertyFlags::HOST_COHERENT
+            }
+        }
+    }
+}
+
+/// Find a memory type allowed by `type_bits` that has all `required` flags,
+/// preferring the one that has the most `preferred` flags.
+pub(crate) fn find_memory_type(
+    memory_properties: &PhysicalDeviceMemoryProperties,
+    type_bits: u32,
+    required: MemoryPropertyFlags,
+    preferred: MemoryPropertyFlags,
+) -> Option<u32> {
+    (0..memory_properties.memory_type_count)
+        .filter(|i| type_bits & (1 << i) != 0)
+        .filter(|i| {
+            memory_properties.memory_types[*i as usize]
+                .property_flags
+                .contains(required)
+        })
+        .max_by_key(|i| {
+            let flags = memory_properties.memory_types[*i as usize].property_flags;
+            // Prefer lower indices on ties, as the spec orders the best types first.
+            ((flags & preferred).as_raw().count_ones(), u32::MAX - i)
+        })
+}
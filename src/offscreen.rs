@@ -0,0 +1,284 @@
+use ash::vk::{
+    AccessFlags, DependencyFlags, Extent2D, Extent3D, Format, FormatFeatureFlags, ImageLayout,
+    ImageUsageFlags, PipelineStageFlags,
+};
+
+use crate::{
+    layout_barrier, AttachmentDesc, AttachmentLoadOp, ClearValue, Device, Error, ErrorKind,
+    FrameBuffer, Gallium, Image, ImageDesc, ImageView, Queue, RenderPass, RenderPassDesc, Result,
+    SubPass,
+};
+
+/// Description for Offscreen Target Creation
+///
+/// The default format is `R8G8B8A8_UNORM` and the target is cleared to opaque black.
+///
+/// # Example
+/// ```
+/// use gallium::{Format, OffscreenTargetDesc};
+///
+/// let desc = OffscreenTargetDesc::new(640, 480)
+///     .format(Format::R8G8B8A8_SRGB)
+///     .clear_color([0.0, 0.0, 0.2, 1.0]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OffscreenTargetDesc {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) format: Format,
+    pub(crate) clear_color: [f32; 4],
+}
+
+impl OffscreenTargetDesc {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            format: Format::R8G8B8A8_UNORM,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Color the target is cleared to at the beginning of each render.
+    pub fn clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+}
+
+/// An image to render to without a window
+///
+/// It bundles the image, its view, a render pass with one color attachment and the frame buffer.
+/// Create pipelines with [OffscreenTarget::render_pass], then draw with
+/// [OffscreenTarget::render]. The image is left in `SHADER_READ_ONLY_OPTIMAL`, so it can also be
+/// sampled by later passes.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Device, GraphicsPipelineDesc, OffscreenTargetDesc, Queue, Shader};
+/// # fn example(device: &Device, queue: &Queue, fragment_shader: Shader, vertex_shader: Shader) {
+///
+/// let target = device
+///     .create_offscreen_target(queue, &OffscreenTargetDesc::new(640, 480))
+///     .unwrap();
+/// let pipeline = target
+///     .render_pass()
+///     .create_graphics_pipeline(
+///         device,
+///         &[fragment_shader, vertex_shader],
+///         &GraphicsPipelineDesc::new(),
+///     )
+///     .unwrap();
+/// target
+///     .render(|gallium| {
+///         gallium.bind_pipeline(device, &pipeline);
+///         gallium.draw(device, 3, 1, 0, 0);
+///     })
+///     .unwrap();
+/// let pixels = target.to_rgba8().unwrap();
+/// # }
+/// ```
+pub struct OffscreenTarget {
+    // Dropped in this order, before the image.
+    gallium: Gallium,
+    frame_buffer: FrameBuffer,
+    render_pass: RenderPass,
+    view: ImageView,
+    image: Image,
+    clear_color: [f32; 4],
+    device: Device,
+    queue: Queue,
+}
+
+impl OffscreenTarget {
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn image_view(&self) -> &ImageView {
+        &self.view
+    }
+
+    pub fn render_pass(&self) -> &RenderPass {
+        &self.render_pass
+    }
+
+    pub fn frame_buffer(&self) -> &FrameBuffer {
+        &self.frame_buffer
+    }
+
+    pub fn extent(&self) -> Extent2D {
+        self.image.extent()
+    }
+
+    pub fn format(&self) -> Format {
+        self.image.format()
+    }
+
+    /// Render to the target and wait until the GPU has finished
+    ///
+    /// The render pass has begun when `record` is called and ends after it returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - Records the draw commands into the given recorder.
+    pub fn render(&self, record: impl FnOnce(&Gallium)) -> Result<()> {
+        let extent = self.extent();
+        self.gallium.begin_draw(&self.device)?;
+        self.gallium.begin_render_pass(
+            &self.device,
+            &self.frame_buffer,
+            &self.render_pass,
+            extent.width,
+            extent.height,
+            &[ClearValue::Color(self.clear_color)],
+        );
+        record(&self.gallium);
+        self.gallium.end_render_pass(&self.device);
+        self.gallium.end_draw(&self.device)?;
+
+        let fence = self.device.create_fence()?;
+        self.device
+            .dispatch_to_queue_with_fence(&self.gallium, &self.queue, &fence)?;
+        fence.wait(None)
+    }
+
+    /// Read the target back as tightly packed 8-bit RGBA rows
+    ///
+    /// Only 8-bit RGBA and BGRA formats are supported. Call it after [OffscreenTarget::render].
+    pub fn to_rgba8(&self) -> Result<Vec<u8>> {
+        match self.image.format() {
+            Format::R8G8B8A8_UNORM
+            | Format::R8G8B8A8_SRGB
+            | Format::B8G8R8A8_UNORM
+            | Format::B8G8R8A8_SRGB => self.image.read_pixels(
+                &self.device,
+                &self.queue,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            format => Err(
+                Error::new(ErrorKind::FormatNotSupported, "OffscreenTarget::to_rgba8")
+                    .with_context(format!("{:?} is not 8-bit RGBA", format)),
+            ),
+        }
+    }
+
+    /// Save the target as a PNG file
+    ///
+    /// See [OffscreenTarget::to_rgba8] for the supported formats.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to create.
+    #[cfg(feature = "png")]
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let path = path.as_ref();
+        let pixels = self.to_rgba8()?;
+        let file = std::fs::File::create(path).map_err(|e| {
+            Error::from_io(e, "OffscreenTarget::save_png").with_context(path.display().to_string())
+        })?;
+        let extent = self.extent();
+        let mut encoder =
+            png::Encoder::new(std::io::BufWriter::new(file), extent.width, extent.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .map_err(|e| {
+                Error::new(ErrorKind::Io, "OffscreenTarget::save_png")
+                    .with_source(e)
+                    .with_context(path.display().to_string())
+            })
+    }
+}
+
+impl Device {
+    /// Create an offscreen render target
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - Queue that renders to the target.
+    /// * `desc` - Size, format and clear color of the target.
+    pub fn create_offscreen_target(
+        &self,
+        queue: &Queue,
+        desc: &OffscreenTargetDesc,
+    ) -> Result<OffscreenTarget> {
+        if desc.width == 0 || desc.height == 0 {
+            return Err(
+                Error::new(ErrorKind::InvalidValue, "Device::create_offscreen_target")
+                    .with_context(format!("size {}x{}", desc.width, desc.height)),
+            );
+        }
+        self.check_format_features(
+            desc.format,
+            FormatFeatureFlags::COLOR_ATTACHMENT | FormatFeatureFlags::TRANSFER_SRC,
+            "Device::create_offscreen_target",
+        )?;
+        let image = self.create_image_with_desc(&ImageDesc {
+            format: desc.format,
+            extent: Extent3D {
+                width: desc.width,
+                height: desc.height,
+                depth: 1,
+            },
+            usage: ImageUsageFlags::COLOR_ATTACHMENT
+                | ImageUsageFlags::TRANSFER_SRC
+                | ImageUsageFlags::SAMPLED,
+            ..Default::default()
+        })?;
+        // The image is always in the final layout of the render pass, even before the first
+        // render, so that `to_rgba8` can transition from it.
+        let to_shader_read = layout_barrier(
+            image.inner,
+            ImageLayout::UNDEFINED,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            AccessFlags::empty(),
+            AccessFlags::SHADER_READ,
+        );
+        self.run_commands(queue, |command_buffer| unsafe {
+            self.inner.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TOP_OF_PIPE,
+                PipelineStageFlags::ALL_COMMANDS,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_shader_read],
+            );
+        })?;
+        let view = image.create_image_view(self)?;
+        let render_pass = self.create_render_pass(&RenderPassDesc {
+            attachments: vec![AttachmentDesc {
+                format: desc.format,
+                load_op: AttachmentLoadOp::CLEAR,
+                final_layout: ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ..Default::default()
+            }],
+            subpasses: vec![SubPass::new()],
+        })?;
+        let frame_buffer = view.create_frame_buffer(self, &render_pass, desc.width, desc.height)?;
+        let gallium = self.create_gallium(queue)?;
+        Ok(OffscreenTarget {
+            gallium,
+            frame_buffer,
+            render_pass,
+            view,
+            image,
+            clear_color: desc.clear_color,
+            device: Device {
+                inner: self.inner.clone(),
+            },
+            queue: Queue {
+                inner: queue.inner,
+                info: queue.info,
+            },
+        })
+    }
+}
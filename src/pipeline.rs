@@ -1,3 +1,696 @@
+use std::sync::Arc;
+
+pub use ash::vk::{
+    BlendFactor, BlendOp, ColorComponentFlags, CompareOp, CullModeFlags, FrontFace, LogicOp,
+    PolygonMode, PrimitiveTopology,
+};
+
+use ash::vk::{
+    ComputePipelineCreateInfo, DynamicState, GraphicsPipelineCreateInfo, PipelineBindPoint,
+    PipelineCache, PipelineColorBlendAttachmentState, PipelineColorBlendStateCreateInfo,
+    PipelineDepthStencilStateCreateInfo, PipelineDynamicStateCreateInfo,
+    PipelineInputAssemblyStateCreateInfo, PipelineLayoutCreateInfo,
+    PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
+    PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo,
+    PipelineViewportStateCreateInfo, SampleCountFlags, ShaderStageFlags,
+};
+
+use crate::{
+    DescriptorSetLayout, Device, Error, ErrorKind, Image, RawDevice, RenderPass, Result, Shader,
+    ShaderKind, VertexLayout,
+};
+
 pub struct Pipeline {
     pub(crate) inner: ash::vk::Pipeline,
+    pub(crate) layout: ash::vk::PipelineLayout,
+    pub(crate) bind_point: PipelineBindPoint,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_pipeline(self.inner, None);
+            self.device.destroy_pipeline_layout(self.layout, None);
+        }
+    }
+}
+
+/// Depth bias applied by the rasterizer
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DepthBias {
+    pub constant_factor: f32,
+    pub clamp: f32,
+    pub slope_factor: f32,
+}
+
+/// Depth test of a graphics pipeline
+///
+/// The subpass must have a depth attachment, see [crate::SubPass::with_attachments].
+/// The default keeps the nearest fragments and writes their depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepthTest {
+    /// Fragments pass if `compare_op(fragment depth, stored depth)` is true.
+    pub compare_op: CompareOp,
+    /// Write the depth of passing fragments to the attachment.
+    pub write: bool,
+}
+
+impl Default for DepthTest {
+    fn default() -> Self {
+        Self {
+            compare_op: CompareOp::LESS,
+            write: true,
+        }
+    }
+}
+
+/// Blend state of a color attachment
+///
+/// # Example
+/// ```
+/// use gallium::{BlendState, GraphicsPipelineDesc};
+///
+/// let desc = GraphicsPipelineDesc::new().blend(&[BlendState::alpha()]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlendState {
+    pub enable: bool,
+    pub src_color_factor: BlendFactor,
+    pub dst_color_factor: BlendFactor,
+    pub color_op: BlendOp,
+    pub src_alpha_factor: BlendFactor,
+    pub dst_alpha_factor: BlendFactor,
+    pub alpha_op: BlendOp,
+    pub write_mask: ColorComponentFlags,
+}
+
+impl Default for BlendState {
+    fn default() -> Self {
+        Self::opaque()
+    }
+}
+
+impl BlendState {
+    /// No blending, the source color replaces the destination.
+    pub fn opaque() -> Self {
+        Self {
+            enable: false,
+            src_color_factor: BlendFactor::ONE,
+            dst_color_factor: BlendFactor::ZERO,
+            color_op: BlendOp::ADD,
+            src_alpha_factor: BlendFactor::ONE,
+            dst_alpha_factor: BlendFactor::ZERO,
+            alpha_op: BlendOp::ADD,
+            write_mask: ColorComponentFlags::RGBA,
+        }
+    }
+
+    /// Standard alpha blending: `src * src.a + dst * (1 - src.a)`.
+    pub fn alpha() -> Self {
+        Self {
+            enable: true,
+            src_color_factor: BlendFactor::SRC_ALPHA,
+            dst_color_factor: BlendFactor::ONE_MINUS_SRC_ALPHA,
+            src_alpha_factor: BlendFactor::ONE,
+            dst_alpha_factor: BlendFactor::ONE_MINUS_SRC_ALPHA,
+            ..Self::opaque()
+        }
+    }
+
+    /// Alpha blending for colors already multiplied by alpha: `src + dst * (1 - src.a)`.
+    pub fn premultiplied_alpha() -> Self {
+        Self {
+            enable: true,
+            src_color_factor: BlendFactor::ONE,
+            dst_color_factor: BlendFactor::ONE_MINUS_SRC_ALPHA,
+            src_alpha_factor: BlendFactor::ONE,
+            dst_alpha_factor: BlendFactor::ONE_MINUS_SRC_ALPHA,
+            ..Self::opaque()
+        }
+    }
+
+    /// Additive blending: `src * src.a + dst`.
+    pub fn additive() -> Self {
+        Self {
+            enable: true,
+            src_color_factor: BlendFactor::SRC_ALPHA,
+            dst_color_factor: BlendFactor::ONE,
+            src_alpha_factor: BlendFactor::ONE,
+            dst_alpha_factor: BlendFactor::ONE,
+            ..Self::opaque()
+        }
+    }
+
+    pub(crate) fn to_vk(self) -> PipelineColorBlendAttachmentState {
+        PipelineColorBlendAttachmentState::builder()
+            .blend_enable(self.enable)
+            .src_color_blend_factor(self.src_color_factor)
+            .dst_color_blend_factor(self.dst_color_factor)
+            .color_blend_op(self.color_op)
+            .src_alpha_blend_factor(self.src_alpha_factor)
+            .dst_alpha_blend_factor(self.dst_alpha_factor)
+            .alpha_blend_op(self.alpha_op)
+            .color_write_mask(self.write_mask)
+            .build()
+    }
+}
+
+/// A range of push constants accessible from some shader stages
+///
+/// # Example
+/// ```
+/// use gallium::{GraphicsPipelineDesc, PushConstantRange, ShaderStageFlags};
+///
+/// // A 4x4 matrix for the vertex shader
+/// let desc = GraphicsPipelineDesc::new()
+///     .push_constant_ranges(&[PushConstantRange::new(ShaderStageFlags::VERTEX, 0, 64)]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PushConstantRange {
+    pub stages: ShaderStageFlags,
+    /// Offset in bytes. It must be a multiple of 4.
+    pub offset: u32,
+    /// Size in bytes. It must be a non-zero multiple of 4.
+    pub size: u32,
+}
+
+impl PushConstantRange {
+    pub fn new(stages: ShaderStageFlags, offset: u32, size: u32) -> Self {
+        Self {
+            stages,
+            offset,
+            size,
+        }
+    }
+
+    pub(crate) fn to_vk(self) -> ash::vk::PushConstantRange {
+        ash::vk::PushConstantRange::builder()
+            .stage_flags(self.stages)
+            .offset(self.offset)
+            .size(self.size)
+            .build()
+    }
+}
+
+/// Check that `offset..offset + size` is a non-empty range that fits in the push constant limit
+/// of the device.
+pub(crate) fn check_push_constant_range(
+    device: &RawDevice,
+    offset: u32,
+    size: usize,
+    operation: &'static str,
+) -> Result<()> {
+    let max = device.properties.limits.max_push_constants_size;
+    match u32::try_from(size).ok().and_then(|s| offset.checked_add(s)) {
+        Some(end)
+            if end <= max && size > 0 && offset.is_multiple_of(4) && size.is_multiple_of(4) =>
+        {
+            Ok(())
+        }
+        _ => Err(Error::new(ErrorKind::InvalidValue, operation).with_context(format!(
+            "push constants at {} of {} bytes are empty, exceed max_push_constants_size {} or are not aligned to 4",
+            offset, size, max
+        ))),
+    }
+}
+
+/// Description of the resources a pipeline can access
+///
+/// # Example
+/// ```no_run
+/// use gallium::{DescriptorSetLayout, PipelineLayoutDesc, PushConstantRange, ShaderStageFlags};
+/// # fn example(set_layout: &DescriptorSetLayout) {
+///
+/// let layout = PipelineLayoutDesc::new()
+///     .set_layouts(&[set_layout])
+///     .push_constant_ranges(&[PushConstantRange::new(ShaderStageFlags::COMPUTE, 0, 16)]);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PipelineLayoutDesc {
+    pub(crate) set_layouts: Vec<DescriptorSetLayout>,
+    pub(crate) push_constant_ranges: Vec<PushConstantRange>,
+}
+
+impl PipelineLayoutDesc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Descriptor set layouts. The set number is the index in `layouts`.
+    pub fn set_layouts(mut self, layouts: &[&DescriptorSetLayout]) -> Self {
+        self.set_layouts = layouts.iter().map(|l| (*l).clone()).collect();
+        self
+    }
+
+    pub fn push_constant_ranges(mut self, ranges: &[PushConstantRange]) -> Self {
+        self.push_constant_ranges = ranges.to_vec();
+        self
+    }
+
+    pub(crate) fn create(
+        &self,
+        device: &RawDevice,
+        operation: &'static str,
+    ) -> Result<ash::vk::PipelineLayout> {
+        for range in &self.push_constant_ranges {
+            check_push_constant_range(device, range.offset, range.size as usize, operation)?;
+        }
+        let set_layouts: Vec<_> = self.set_layouts.iter().map(|l| l.inner.inner).collect();
+        let push_constant_ranges: Vec<_> = self
+            .push_constant_ranges
+            .iter()
+            .map(|r| r.to_vk())
+            .collect();
+        let create_info = PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(&push_constant_ranges)
+            .build();
+        unsafe { device.create_pipeline_layout(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreatePipelineLayout"))
+    }
+}
+
+/// Description of the fixed-function state of a graphics pipeline
+///
+/// The default has no descriptor sets, push constants or vertex input, and is a filled
+/// triangle list with back-face culling, clockwise front faces, no depth test, single sampling
+/// and one opaque color attachment.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{
+///     BlendState, CullModeFlags, DepthTest, Device, GraphicsPipelineDesc, PrimitiveTopology,
+///     RenderPass, Shader,
+/// };
+/// # fn example(
+/// #     device: &Device,
+/// #     render_pass: &RenderPass,
+/// #     fragment_shader: Shader,
+/// #     vertex_shader: Shader,
+/// # ) {
+///
+/// let desc = GraphicsPipelineDesc::new()
+///     .topology(PrimitiveTopology::LINE_STRIP)
+///     .cull_mode(CullModeFlags::NONE)
+///     .depth_test(Some(DepthTest::default()))
+///     .blend(&[BlendState::additive()]);
+/// let pipeline = render_pass
+///     .create_graphics_pipeline(device, &[fragment_shader, vertex_shader], &desc)
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct GraphicsPipelineDesc {
+    pub(crate) layout: PipelineLayoutDesc,
+    pub(crate) vertex_layouts: Vec<VertexLayout>,
+    pub(crate) topology: PrimitiveTopology,
+    pub(crate) primitive_restart: bool,
+    pub(crate) polygon_mode: PolygonMode,
+    pub(crate) cull_mode: CullModeFlags,
+    pub(crate) front_face: FrontFace,
+    pub(crate) line_width: f32,
+    pub(crate) depth_bias: Option<DepthBias>,
+    pub(crate) depth_test: Option<DepthTest>,
+    pub(crate) samples: SampleCountFlags,
+    pub(crate) blend: Vec<BlendState>,
+    pub(crate) blend_constants: [f32; 4],
+    pub(crate) logic_op: Option<LogicOp>,
+    pub(crate) subpass: u32,
+}
+
+impl Default for GraphicsPipelineDesc {
+    fn default() -> Self {
+        Self {
+            layout: PipelineLayoutDesc::default(),
+            vertex_layouts: vec![],
+            topology: PrimitiveTopology::TRIANGLE_LIST,
+            primitive_restart: false,
+            polygon_mode: PolygonMode::FILL,
+            cull_mode: CullModeFlags::BACK,
+            front_face: FrontFace::CLOCKWISE,
+            line_width: 1.0,
+            depth_bias: None,
+            depth_test: None,
+            samples: SampleCountFlags::TYPE_1,
+            blend: vec![BlendState::opaque()],
+            blend_constants: [0.0; 4],
+            logic_op: None,
+            subpass: 0,
+        }
+    }
+}
+
+impl GraphicsPipelineDesc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Descriptor set layouts of the pipeline layout. The set number is the index in `layouts`.
+    pub fn set_layouts(mut self, layouts: &[&DescriptorSetLayout]) -> Self {
+        self.layout = self.layout.set_layouts(layouts);
+        self
+    }
+
+    /// Push constant ranges of the pipeline layout.
+    pub fn push_constant_ranges(mut self, ranges: &[PushConstantRange]) -> Self {
+        self.layout = self.layout.push_constant_ranges(ranges);
+        self
+    }
+
+    /// Layout of each vertex buffer binding. The binding number is the index in `layouts`.
+    pub fn vertex_layouts(mut self, layouts: &[VertexLayout]) -> Self {
+        self.vertex_layouts = layouts.to_vec();
+        self
+    }
+
+    pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Restart strip and fan primitives at the special index `0xFFFF` or `0xFFFFFFFF`.
+    pub fn primitive_restart(mut self, enable: bool) -> Self {
+        self.primitive_restart = enable;
+        self
+    }
+
+    /// Modes other than `FILL` require the `fillModeNonSolid` feature.
+    pub fn polygon_mode(mut self, polygon_mode: PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: CullModeFlags) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn front_face(mut self, front_face: FrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    /// Width of rasterized lines. Values other than 1.0 require the `wideLines` feature.
+    pub fn line_width(mut self, line_width: f32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Depth bias added to the depth of fragments before the depth test.
+    pub fn depth_bias(mut self, depth_bias: Option<DepthBias>) -> Self {
+        self.depth_bias = depth_bias;
+        self
+    }
+
+    /// Depth test and depth write. `None` disables both.
+    pub fn depth_test(mut self, depth_test: Option<DepthTest>) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    /// Sample count. It must match the attachments of the render pass.
+    pub fn samples(mut self, samples: SampleCountFlags) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Blend state of each color attachment of the subpass, in order. There must be one per
+    /// color attachment.
+    pub fn blend(mut self, blend: &[BlendState]) -> Self {
+        self.blend = blend.to_vec();
+        self
+    }
+
+    pub fn blend_constants(mut self, blend_constants: [f32; 4]) -> Self {
+        self.blend_constants = blend_constants;
+        self
+    }
+
+    /// Replaces blending with a logical operation. Requires the `logicOp` feature.
+    pub fn logic_op(mut self, logic_op: Option<LogicOp>) -> Self {
+        self.logic_op = logic_op;
+        self
+    }
+
+    /// Index of the subpass in which the pipeline is used.
+    pub fn subpass(mut self, subpass: u32) -> Self {
+        self.subpass = subpass;
+        self
+    }
+}
+
+impl RenderPass {
+    /// Create a graphics pipeline with the default [GraphicsPipelineDesc].
+    ///
+    /// `image` is unused and only kept for compatibility; prefer
+    /// [RenderPass::create_graphics_pipeline].
+    pub fn create_pipeline(
+        &self,
+        _image: &Image,
+        device: &Device,
+        shaders: &[Shader],
+    ) -> Result<Vec<Pipeline>> {
+        let pipeline =
+            self.create_graphics_pipeline(device, shaders, &GraphicsPipelineDesc::new())?;
+        Ok(vec![pipeline])
+    }
+
+    /// Create a graphics pipeline
+    ///
+    /// Returns an error of kind `ErrorKind::FeatureNotPresent` if `desc` uses a polygon mode,
+    /// line width or logic op the GPU does not support.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - Valid Devices
+    /// * `shaders` - Shaders of each stage.
+    /// * `desc` - Fixed-function state of the pipeline.
+    pub fn create_graphics_pipeline(
+        &self,
+        device: &Device,
+        shaders: &[Shader],
+        desc: &GraphicsPipelineDesc,
+    ) -> Result<Pipeline> {
+        if shaders.is_empty() {
+            return Err(
+                Error::new(ErrorKind::InvalidValue, "RenderPass::create_pipeline")
+                    .with_context("no shaders given"),
+            );
+        }
+        match self.color_attachment_counts.get(desc.subpass as usize) {
+            None => {
+                return Err(
+                    Error::new(ErrorKind::InvalidValue, "RenderPass::create_pipeline")
+                        .with_context(format!(
+                            "subpass {} of a render pass with {}",
+                            desc.subpass,
+                            self.color_attachment_counts.len()
+                        )),
+                )
+            }
+            Some(&count) if count != desc.blend.len() => {
+                return Err(
+                    Error::new(ErrorKind::InvalidValue, "RenderPass::create_pipeline")
+                        .with_context(format!(
+                            "{} blend states for {} color attachments",
+                            desc.blend.len(),
+                            count
+                        )),
+                )
+            }
+            Some(_) => (),
+        }
+        let features = &device.inner.features;
+        let missing_feature = if desc.polygon_mode != PolygonMode::FILL
+            && features.fill_mode_non_solid != ash::vk::TRUE
+        {
+            Some("fillModeNonSolid")
+        } else if desc.line_width != 1.0 && features.wide_lines != ash::vk::TRUE {
+            Some("wideLines")
+        } else if desc.logic_op.is_some() && features.logic_op != ash::vk::TRUE {
+            Some("logicOp")
+        } else {
+            None
+        };
+        if let Some(feature) = missing_feature {
+            return Err(
+                Error::new(ErrorKind::FeatureNotPresent, "RenderPass::create_pipeline")
+                    .with_context(format!("{} is not supported by the GPU", feature)),
+            );
+        }
+        let mut shader_stages = vec![];
+        let entry = c"main";
+        for i in shaders {
+            let flag = match i.kind {
+                ShaderKind::Vertex => ShaderStageFlags::VERTEX,
+                ShaderKind::Fragment => ShaderStageFlags::FRAGMENT,
+                ShaderKind::Compute => {
+                    return Err(
+                        Error::new(ErrorKind::InvalidValue, "RenderPass::create_pipeline")
+                            .with_context("a compute shader cannot be used in a graphics pipeline"),
+                    )
+                }
+            };
+            shader_stages.push(
+                PipelineShaderStageCreateInfo::builder()
+                    .module(i.inner)
+                    .name(entry)
+                    .stage(flag)
+                    .build(),
+            );
+        }
+        // Pipelines stay valid when the swapchain is resized.
+        let viewport_state_info = PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1)
+            .build();
+        let dynamic_states = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
+        let dynamic_state = PipelineDynamicStateCreateInfo::builder()
+            .dynamic_states(&dynamic_states)
+            .build();
+        let (vertex_bindings, vertex_attributes) = VertexLayout::to_vk(&desc.vertex_layouts);
+        let vertex_input_info = PipelineVertexInputStateCreateInfo::builder()
+            .vertex_attribute_descriptions(&vertex_attributes)
+            .vertex_binding_descriptions(&vertex_bindings)
+            .build();
+        let input_assembly = PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(desc.topology)
+            .primitive_restart_enable(desc.primitive_restart)
+            .build();
+        let depth_bias = desc.depth_bias.unwrap_or_default();
+        let rasterizer = PipelineRasterizationStateCreateInfo::builder()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(desc.polygon_mode)
+            .line_width(desc.line_width)
+            .cull_mode(desc.cull_mode)
+            .front_face(desc.front_face)
+            .depth_bias_enable(desc.depth_bias.is_some())
+            .depth_bias_constant_factor(depth_bias.constant_factor)
+            .depth_bias_clamp(depth_bias.clamp)
+            .depth_bias_slope_factor(depth_bias.slope_factor)
+            .build();
+        let depth_stencil = PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(desc.depth_test.is_some())
+            .depth_write_enable(desc.depth_test.is_some_and(|d| d.write))
+            .depth_compare_op(desc.depth_test.map_or(CompareOp::ALWAYS, |d| d.compare_op))
+            .depth_bounds_test_enable(false)
+            .stencil_test_enable(false)
+            .min_depth_bounds(0.0)
+            .max_depth_bounds(1.0)
+            .build();
+        let multisample = PipelineMultisampleStateCreateInfo::builder()
+            .sample_shading_enable(false)
+            .rasterization_samples(desc.samples)
+            .build();
+        let blend_attachment: Vec<_> = desc.blend.iter().map(|b| b.to_vk()).collect();
+        let blend = PipelineColorBlendStateCreateInfo::builder()
+            .logic_op_enable(desc.logic_op.is_some())
+            .logic_op(desc.logic_op.unwrap_or(LogicOp::COPY))
+            .attachments(&blend_attachment)
+            .blend_constants(desc.blend_constants)
+            .build();
+        let pipeline_layout = desc
+            .layout
+            .create(&device.inner, "RenderPass::create_pipeline")?;
+
+        let pipeline_create_info = GraphicsPipelineCreateInfo::builder()
+            .viewport_state(&viewport_state_info)
+            .vertex_input_state(&vertex_input_info)
+            .input_assembly_state(&input_assembly)
+            .rasterization_state(&rasterizer)
+            .multisample_state(&multisample)
+            .depth_stencil_state(&depth_stencil)
+            .dynamic_state(&dynamic_state)
+            .color_blend_state(&blend)
+            .layout(pipeline_layout)
+            .render_pass(self.inner)
+            .subpass(desc.subpass)
+            .stages(&shader_stages)
+            .build();
+
+        let pipeline = match unsafe {
+            device.inner.create_graphics_pipelines(
+                PipelineCache::null(),
+                &[pipeline_create_info],
+                None,
+            )
+        } {
+            Ok(p) => p[0],
+            Err((_, e)) => {
+                unsafe { device.inner.destroy_pipeline_layout(pipeline_layout, None) };
+                return Err(Error::from_vk(e, "vkCreateGraphicsPipelines"));
+            }
+        };
+
+        Ok(Pipeline {
+            inner: pipeline,
+            layout: pipeline_layout,
+            bind_point: PipelineBindPoint::GRAPHICS,
+            device: device.inner.clone(),
+        })
+    }
+}
+
+impl Device {
+    /// Create a compute pipeline
+    ///
+    /// # Arguments
+    ///
+    /// * `shader` - Shader created with `ShaderKind::Compute`. Its entry point must be `main`.
+    /// * `layout` - Descriptor sets and push constants of the pipeline.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gallium::{DescriptorSetLayout, Device, PipelineLayoutDesc, ShaderKind, Spirv};
+    /// # fn example(device: &Device, set_layout: &DescriptorSetLayout) {
+    ///
+    /// let shader = device
+    ///     .create_shader_module(Spirv::new("reduce.comp.spv").unwrap(), ShaderKind::Compute)
+    ///     .unwrap();
+    /// let pipeline = device
+    ///     .create_compute_pipeline(&shader, &PipelineLayoutDesc::new().set_layouts(&[set_layout]))
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn create_compute_pipeline(
+        &self,
+        shader: &Shader,
+        layout: &PipelineLayoutDesc,
+    ) -> Result<Pipeline> {
+        if !matches!(shader.kind, ShaderKind::Compute) {
+            return Err(
+                Error::new(ErrorKind::InvalidValue, "Device::create_compute_pipeline")
+                    .with_context(format!("expected a compute shader, got {:?}", shader.kind)),
+            );
+        }
+        let pipeline_layout = layout.create(&self.inner, "Device::create_compute_pipeline")?;
+        let stage = PipelineShaderStageCreateInfo::builder()
+            .module(shader.inner)
+            .name(c"main")
+            .stage(ShaderStageFlags::COMPUTE)
+            .build();
+        let create_info = ComputePipelineCreateInfo::builder()
+            .stage(stage)
+            .layout(pipeline_layout)
+            .build();
+        let pipeline = match unsafe {
+            self.inner
+                .create_compute_pipelines(PipelineCache::null(), &[create_info], None)
+        } {
+            Ok(p) => p[0],
+            Err((_, e)) => {
+                unsafe { self.inner.destroy_pipeline_layout(pipeline_layout, None) };
+                return Err(Error::from_vk(e, "vkCreateComputePipelines"));
+            }
+        };
+        Ok(Pipeline {
+            inner: pipeline,
+            layout: pipeline_layout,
+            bind_point: PipelineBindPoint::COMPUTE,
+            device: self.inner.clone(),
+        })
+    }
 }
@@ -1,36 +1,47 @@
+#[cfg(feature = "surface")]
 use ash::vk::PresentInfoKHR;
 
-use crate::Swapchain;
+#[cfg(feature = "surface")]
+use crate::{Error, PresentResult, Result, Semaphore, Swapchain};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct GPUQueueInfo {
     pub(crate) index: u32,
     pub(crate) count: u32,
 }
 
-impl Default for GPUQueueInfo {
-    fn default() -> Self {
-        Self {
-            index: Default::default(),
-            count: Default::default(),
-        }
-    }
-}
-
 pub struct Queue {
     pub(crate) inner: ash::vk::Queue,
     pub(crate) info: GPUQueueInfo,
 }
 
 impl Queue {
-    pub fn present(&self, swapchain: &Swapchain, index: usize) {
+    /// Present a swapchain image
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index returned by `Device::acquire_next_image`.
+    /// * `wait` - Semaphores signaled when rendering to the image has finished.
+    #[cfg(feature = "surface")]
+    pub fn present(
+        &self,
+        swapchain: &Swapchain,
+        index: usize,
+        wait: &[&Semaphore],
+    ) -> Result<PresentResult> {
+        let wait_semaphores: Vec<_> = wait.iter().map(|s| s.inner).collect();
+        let swapchains = [swapchain.khr];
+        let image_indices = [index as u32];
         let present_info = PresentInfoKHR::builder()
-            .swapchains(&[swapchain.khr])
-            .image_indices(&[index as u32])
+            .wait_semaphores(&wait_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices)
             .build();
         match unsafe { swapchain.inner.queue_present(self.inner, &present_info) } {
-            Ok(e) => {}
-            Err(_) => panic!("Err"),
+            Ok(false) => Ok(PresentResult::Ok),
+            Ok(true) => Ok(PresentResult::Suboptimal),
+            Err(ash::vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(PresentResult::OutOfDate),
+            Err(e) => Err(Error::from_vk(e, "vkQueuePresentKHR")),
         }
     }
 }
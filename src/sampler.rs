@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+pub use ash::vk::{Filter, SamplerAddressMode, SamplerMipmapMode};
+
+use ash::vk::{BorderColor, CompareOp, SamplerCreateInfo, LOD_CLAMP_NONE};
+
+use crate::{Device, Error, ErrorKind, RawDevice, Result};
+
+/// Description for Sampler Creation
+///
+/// The default samples linearly and repeats the texture.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Filter, SamplerAddressMode, SamplerDesc};
+/// # fn example(device: &gallium::Device) {
+///
+/// let sampler = device
+///     .create_sampler(&SamplerDesc {
+///         mag_filter: Filter::NEAREST,
+///         address_mode: SamplerAddressMode::CLAMP_TO_EDGE,
+///         ..Default::default()
+///     })
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerDesc {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: SamplerMipmapMode,
+    /// Address mode of the u, v and w coordinates.
+    pub address_mode: SamplerAddressMode,
+    /// Requires the `samplerAnisotropy` feature.
+    pub max_anisotropy: Option<f32>,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self {
+            mag_filter: Filter::LINEAR,
+            min_filter: Filter::LINEAR,
+            mipmap_mode: SamplerMipmapMode::LINEAR,
+            address_mode: SamplerAddressMode::REPEAT,
+            max_anisotropy: None,
+        }
+    }
+}
+
+pub struct Sampler {
+    pub(crate) inner: ash::vk::Sampler,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_sampler(self.inner, None);
+        }
+    }
+}
+
+impl Device {
+    /// Create a sampler
+    ///
+    /// # Arguments
+    ///
+    /// * `desc` - Filtering and addressing of the sampler.
+    ///
+    /// Returns an error of kind `ErrorKind::FeatureNotPresent` if `desc.max_anisotropy` is set
+    /// and the GPU does not support `samplerAnisotropy`.
+    pub fn create_sampler(&self, desc: &SamplerDesc) -> Result<Sampler> {
+        if desc.max_anisotropy.is_some() && self.inner.features.sampler_anisotropy != ash::vk::TRUE
+        {
+            return Err(
+                Error::new(ErrorKind::FeatureNotPresent, "Device::create_sampler")
+                    .with_context("samplerAnisotropy is not supported by the GPU"),
+            );
+        }
+        let create_info = SamplerCreateInfo::builder()
+            .mag_filter(desc.mag_filter)
+            .min_filter(desc.min_filter)
+            .mipmap_mode(desc.mipmap_mode)
+            .address_mode_u(desc.address_mode)
+            .address_mode_v(desc.address_mode)
+            .address_mode_w(desc.address_mode)
+            .anisotropy_enable(desc.max_anisotropy.is_some())
+            .max_anisotropy(desc.max_anisotropy.unwrap_or(1.0))
+            .compare_enable(false)
+            .compare_op(CompareOp::ALWAYS)
+            .min_lod(0.0)
+            .max_lod(LOD_CLAMP_NONE)
+            .border_color(BorderColor::FLOAT_TRANSPARENT_BLACK)
+            .unnormalized_coordinates(false)
+            .build();
+        let inner = unsafe { self.inner.create_sampler(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateSampler"))?;
+        Ok(Sampler {
+            inner,
+            device: self.inner.clone(),
+        })
+    }
+}
@@ -0,0 +1,273 @@
+use std::{sync::Arc, time::Duration};
+
+pub use ash::vk::PipelineStageFlags;
+
+use ash::vk::{
+    SemaphoreCreateInfo, SemaphoreSignalInfo, SemaphoreType, SemaphoreTypeCreateInfo,
+    SemaphoreWaitInfo, SubmitInfo, TimelineSemaphoreSubmitInfo,
+};
+
+use crate::{timeout_nanos, Device, Error, ErrorKind, Fence, Gallium, Queue, RawDevice, Result};
+
+/// Orders work between queue operations on the GPU
+///
+/// It can be created with create_semaphore from Device.
+/// Unlike [Fence], the CPU cannot wait on it.
+pub struct Semaphore {
+    pub(crate) inner: ash::vk::Semaphore,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_semaphore(self.inner, None);
+        }
+    }
+}
+
+/// A semaphore whose state is a 64-bit counter that only increases
+///
+/// It can be created with create_timeline_semaphore from Device.
+/// Submissions wait until the counter reaches a value and set it when they finish,
+/// and the CPU can signal and wait on it as well.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Device, Gallium, PipelineStageFlags, Queue, SubmitDesc};
+/// # fn example(device: &Device, transfer_queue: &Queue, queue: &Queue, upload: &Gallium,
+/// #     gallium: &Gallium) {
+///
+/// // The queues come from two families, see `Instance::create_device_with_queues`.
+/// let timeline = device.create_timeline_semaphore(0).unwrap();
+/// // The upload signals 1, and rendering waits for it.
+/// device
+///     .submit(transfer_queue, &SubmitDesc::new().gallium(upload).signal_timeline(&timeline, 1))
+///     .unwrap();
+/// device
+///     .submit(
+///         queue,
+///         &SubmitDesc::new()
+///             .wait_timeline(&timeline, 1, PipelineStageFlags::VERTEX_INPUT)
+///             .gallium(gallium)
+///             .signal_timeline(&timeline, 2),
+///     )
+///     .unwrap();
+/// timeline.wait(2, None).unwrap();
+/// # }
+/// ```
+pub struct TimelineSemaphore {
+    pub(crate) inner: ash::vk::Semaphore,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl Drop for TimelineSemaphore {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_semaphore(self.inner, None);
+        }
+    }
+}
+
+impl TimelineSemaphore {
+    /// Current value of the counter.
+    pub fn value(&self) -> Result<u64> {
+        unsafe { self.device.get_semaphore_counter_value(self.inner) }
+            .map_err(|e| Error::from_vk(e, "vkGetSemaphoreCounterValue"))
+    }
+
+    /// Set the counter to `value` from the CPU. It must be greater than the current value.
+    pub fn signal(&self, value: u64) -> Result<()> {
+        let signal_info = SemaphoreSignalInfo::builder()
+            .semaphore(self.inner)
+            .value(value)
+            .build();
+        unsafe { self.device.signal_semaphore(&signal_info) }
+            .map_err(|e| Error::from_vk(e, "vkSignalSemaphore"))
+    }
+
+    /// Wait until the counter reaches `value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Maximum time to wait. `None` waits forever.
+    ///
+    /// Returns an error of kind `ErrorKind::Timeout` if the time runs out.
+    pub fn wait(&self, value: u64, timeout: Option<Duration>) -> Result<()> {
+        let semaphores = [self.inner];
+        let values = [value];
+        let wait_info = SemaphoreWaitInfo::builder()
+            .semaphores(&semaphores)
+            .values(&values)
+            .build();
+        unsafe {
+            self.device
+                .wait_semaphores(&wait_info, timeout_nanos(timeout))
+        }
+        .map_err(|e| Error::from_vk(e, "vkWaitSemaphores"))
+    }
+}
+
+/// Description of a queue submission
+///
+/// The commands of the recorders run after all wait semaphores are signaled, and the signal
+/// semaphores and the fence are signaled once the commands have finished.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Device, Gallium, PipelineStageFlags, Queue, SubmitDesc, Swapchain};
+/// # fn example(device: &Device, queue: &Queue, swapchain: &Swapchain, gallium: &Gallium) {
+///
+/// let image_available = device.create_semaphore().unwrap();
+/// let render_finished = device.create_semaphore().unwrap();
+/// let in_flight = device.create_fence().unwrap();
+/// let Some(index) = device
+///     .acquire_next_image(swapchain, Some(&image_available), None)
+///     .unwrap()
+///     .index()
+/// else {
+///     return; // Recreate the swapchain
+/// };
+/// // record gallium ...
+/// device
+///     .submit(
+///         queue,
+///         &SubmitDesc::new()
+///             .wait(&image_available, PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+///             .gallium(gallium)
+///             .signal(&render_finished)
+///             .fence(&in_flight),
+///     )
+///     .unwrap();
+/// queue.present(swapchain, index, &[&render_finished]).unwrap();
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct SubmitDesc<'a> {
+    // Binary semaphores have no value; they are given 0 when timeline values are chained.
+    pub(crate) wait: Vec<(ash::vk::Semaphore, PipelineStageFlags, Option<u64>)>,
+    pub(crate) galliums: Vec<&'a Gallium>,
+    pub(crate) signal: Vec<(ash::vk::Semaphore, Option<u64>)>,
+    pub(crate) fence: Option<&'a Fence>,
+}
+
+impl<'a> SubmitDesc<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait on `semaphore` before the `stage` of the commands runs.
+    pub fn wait(mut self, semaphore: &'a Semaphore, stage: PipelineStageFlags) -> Self {
+        self.wait.push((semaphore.inner, stage, None));
+        self
+    }
+
+    /// Wait until the counter of `semaphore` reaches `value` before the `stage` of the commands runs.
+    pub fn wait_timeline(
+        mut self,
+        semaphore: &'a TimelineSemaphore,
+        value: u64,
+        stage: PipelineStageFlags,
+    ) -> Self {
+        self.wait.push((semaphore.inner, stage, Some(value)));
+        self
+    }
+
+    /// Add a recorder whose commands are submitted.
+    pub fn gallium(mut self, gallium: &'a Gallium) -> Self {
+        self.galliums.push(gallium);
+        self
+    }
+
+    /// Signal `semaphore` when the commands have finished.
+    pub fn signal(mut self, semaphore: &'a Semaphore) -> Self {
+        self.signal.push((semaphore.inner, None));
+        self
+    }
+
+    /// Set the counter of `semaphore` to `value` when the commands have finished.
+    pub fn signal_timeline(mut self, semaphore: &'a TimelineSemaphore, value: u64) -> Self {
+        self.signal.push((semaphore.inner, Some(value)));
+        self
+    }
+
+    /// Signal `fence` when the commands have finished. The fence must be unsignaled.
+    pub fn fence(mut self, fence: &'a Fence) -> Self {
+        self.fence = Some(fence);
+        self
+    }
+}
+
+impl Device {
+    pub fn create_semaphore(&self) -> Result<Semaphore> {
+        let create_info = SemaphoreCreateInfo::builder().build();
+        let inner = unsafe { self.inner.create_semaphore(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateSemaphore"))?;
+        Ok(Semaphore {
+            inner,
+            device: self.inner.clone(),
+        })
+    }
+
+    /// Create a timeline semaphore
+    ///
+    /// # Arguments
+    ///
+    /// * `initial_value` - Initial value of the counter.
+    ///
+    /// Returns an error of kind `ErrorKind::FeatureNotPresent` if the GPU or the Vulkan loader
+    /// does not support Vulkan 1.2 timeline semaphores.
+    pub fn create_timeline_semaphore(&self, initial_value: u64) -> Result<TimelineSemaphore> {
+        if !self.inner.timeline_semaphore {
+            return Err(Error::new(
+                ErrorKind::FeatureNotPresent,
+                "Device::create_timeline_semaphore",
+            )
+            .with_context("timelineSemaphore is not supported by the GPU or the loader"));
+        }
+        let mut type_info = SemaphoreTypeCreateInfo::builder()
+            .semaphore_type(SemaphoreType::TIMELINE)
+            .initial_value(initial_value);
+        let create_info = SemaphoreCreateInfo::builder()
+            .push_next(&mut type_info)
+            .build();
+        let inner = unsafe { self.inner.create_semaphore(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateSemaphore"))?;
+        Ok(TimelineSemaphore {
+            inner,
+            device: self.inner.clone(),
+        })
+    }
+
+    /// Submit work to `queue`
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - Queue of the family the recorders were created for.
+    /// * `desc` - Recorders to run and the semaphores and fence that order them.
+    pub fn submit(&self, queue: &Queue, desc: &SubmitDesc) -> Result<()> {
+        let wait_semaphores: Vec<_> = desc.wait.iter().map(|(s, _, _)| *s).collect();
+        let wait_stages: Vec<_> = desc.wait.iter().map(|(_, stage, _)| *stage).collect();
+        let wait_values: Vec<_> = desc.wait.iter().map(|(_, _, v)| v.unwrap_or(0)).collect();
+        let command_buffers: Vec<_> = desc.galliums.iter().map(|g| g.command_buffer).collect();
+        let signal_semaphores: Vec<_> = desc.signal.iter().map(|(s, _)| *s).collect();
+        let signal_values: Vec<_> = desc.signal.iter().map(|(_, v)| v.unwrap_or(0)).collect();
+        let mut timeline_info = TimelineSemaphoreSubmitInfo::builder()
+            .wait_semaphore_values(&wait_values)
+            .signal_semaphore_values(&signal_values);
+        let mut submit_info = SubmitInfo::builder()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(&signal_semaphores);
+        let has_timeline = desc.wait.iter().any(|(_, _, v)| v.is_some())
+            || desc.signal.iter().any(|(_, v)| v.is_some());
+        if has_timeline {
+            submit_info = submit_info.push_next(&mut timeline_info);
+        }
+        let submit_info = submit_info.build();
+        let fence = desc.fence.map_or(ash::vk::Fence::null(), |f| f.inner);
+        unsafe { self.inner.queue_submit(queue.inner, &[submit_info], fence) }
+            .map_err(|e| Error::from_vk(e, "vkQueueSubmit"))
+    }
+}
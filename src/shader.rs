@@ -1,15 +1,23 @@
 use ash::{util::read_spv, vk::ShaderModule};
-use std::io::{Cursor, Read};
+use std::{
+    fmt,
+    io::{Cursor, Read},
+    sync::Arc,
+};
+
+use crate::{Error, ErrorKind, RawDevice, Result};
 
 ///Indicates shader type
 ///
 /// # Value Meaning
 /// * `Vertex` - Vertex shader.
 /// * `Fragment` - Fragment shader.
-#[derive(Debug)]
+/// * `Compute` - Compute shader, used with [Device::create_compute_pipeline](crate::Device::create_compute_pipeline).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ShaderKind {
     Vertex,
     Fragment,
+    Compute,
 }
 
 /// Represents a Spir-V intermediate representation
@@ -17,13 +25,17 @@ pub enum ShaderKind {
 /// This structure contains binary data that has been processed so that Vulkan can read it
 ///
 /// # Example
-/// ```
+/// ```no_run
+/// use gallium::{Device, ShaderKind, Spirv};
+/// # fn example(device: &Device) {
+///
 /// let fragment_shader = device
 /// .create_shader_module(
-///     Spirv::new("examples/shader/shader.frag.spv"),
+///     Spirv::new("examples/shader/shader.frag.spv").unwrap(),
 ///     ShaderKind::Fragment,
 /// )
 /// .unwrap();
+/// # }
 /// ```
 pub struct Spirv {
     pub(crate) data: Vec<u32>,
@@ -34,22 +46,43 @@ impl Spirv {
     /// # Arguments
     ///
     /// * `file` - Spv file path.
-    pub fn new(file: &str) -> Self {
-        let mut file = std::fs::File::open(file).expect("file open failed");
+    pub fn new(file: &str) -> Result<Self> {
         let mut buf = Vec::new();
-        file.read_to_end(&mut buf).expect("file read failed");
+        std::fs::File::open(file)
+            .and_then(|mut f| f.read_to_end(&mut buf))
+            .map_err(|e| Error::from_io(e, "Spirv::new").with_context(file))?;
         let mut spirv_file = Cursor::new(&buf);
-        let spirv = read_spv(&mut spirv_file).unwrap();
+        let spirv = read_spv(&mut spirv_file).map_err(|e| {
+            Error::new(ErrorKind::InvalidValue, "Spirv::new")
+                .with_context(format!("{}: {}", file, e))
+        })?;
 
-        Self { data: spirv }
+        Ok(Self { data: spirv })
     }
 }
 
 /// Represents a shader
 ///
 /// It can be created with create_shader_module from Device
-#[derive(Debug)]
 pub struct Shader {
     pub(crate) inner: ShaderModule,
     pub(crate) kind: ShaderKind,
+    pub(crate) device: Arc<RawDevice>,
+}
+
+impl fmt::Debug for Shader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shader")
+            .field("inner", &self.inner)
+            .field("kind", &self.kind)
+            .finish()
+    }
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_shader_module(self.inner, None);
+        }
+    }
 }
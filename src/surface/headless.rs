@@ -0,0 +1,52 @@
+use ash::{extensions::ext::HeadlessSurface, vk::HeadlessSurfaceCreateInfoEXT};
+
+use crate::{Error, Instance, Result, Surface};
+
+impl Surface {
+    /// Create a surface without a window
+    ///
+    /// Swapchains of the surface can be acquired and presented like a window's, but the images
+    /// are never shown. This is meant for testing the presentation path without a display,
+    /// e.g. on CI with lavapipe.
+    ///
+    /// The instance must be created with `Instance::new_headless`, otherwise an error of kind
+    /// `ErrorKind::ExtensionNotPresent` is returned.
+    /// The surface has no size of its own, so set `SwapchainDesc::extent` for its swapchains.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gallium::{Extent2D, GPUQueueInfo, Instance, InstanceDesc, Surface, SwapchainDesc};
+    ///
+    /// let instance = Instance::new_headless(InstanceDesc::default()).unwrap();
+    /// let gpu = &instance.enumerate_gpu().unwrap()[0];
+    /// let mut info = GPUQueueInfo::default();
+    /// gpu.is_support_graphics(&instance, &mut info);
+    /// let device = instance.create_device(gpu, info).unwrap();
+    /// let surface = Surface::create_headless(&instance).unwrap();
+    /// let swapchain = device
+    ///     .create_swapchain_with_desc(
+    ///         &instance,
+    ///         gpu,
+    ///         &surface,
+    ///         &SwapchainDesc {
+    ///             extent: Some(Extent2D { width: 640, height: 480 }),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .unwrap();
+    /// ```
+    pub fn create_headless(instance: &Instance) -> Result<Self> {
+        instance.require_extensions(
+            &[
+                ash::extensions::khr::Surface::name(),
+                HeadlessSurface::name(),
+            ],
+            "Surface::create_headless",
+        )?;
+        let create_info = HeadlessSurfaceCreateInfoEXT::builder().build();
+        let loader = HeadlessSurface::new(&instance.entry, &instance.instance);
+        let khr = unsafe { loader.create_headless_surface(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateHeadlessSurfaceEXT"))?;
+        Ok(Self::from_khr(instance, khr))
+    }
+}
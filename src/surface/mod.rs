@@ -1,39 +1,110 @@
-use crate::Instance;
+use crate::{Error, Instance, RawInstance, Result};
 
-use std::ffi::c_void;
+use std::{ffi::CStr, sync::Arc};
 
 use ash::vk::SurfaceKHR;
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 
+#[cfg(feature = "headless_surface")]
+mod headless;
+#[cfg(feature = "wayland_surface")]
+mod wayland;
 #[cfg(feature = "win32_surface")]
-pub type HWND = *const c_void;
+mod win32;
+#[cfg(feature = "xcb_surface")]
+mod xcb;
+#[cfg(feature = "xlib_surface")]
+mod xlib;
+
+#[cfg(feature = "wayland_surface")]
+pub use wayland::*;
 #[cfg(feature = "win32_surface")]
-pub type HINSTANCE = *const c_void;
+pub use win32::*;
+#[cfg(feature = "xcb_surface")]
+pub use xcb::*;
+#[cfg(feature = "xlib_surface")]
+pub use xlib::*;
 
+/// Represents a window to present to
+///
+/// It is created with `Surface::new` from any window implementing `raw-window-handle`, or
+/// with one of the platform constructors, e.g. `create_for_win32` or `create_for_wayland`,
+/// each behind the cargo feature of the same platform. `create_headless` creates one without
+/// a window.
+/// The instance must be created with `Instance::new_with_surface` for the window, otherwise the
+/// `Surface::new` and the platform constructors return an error of kind `ErrorKind::ExtensionNotPresent`.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Instance, InstanceDesc, Surface};
+/// # let event_loop = winit::event_loop::EventLoop::new();
+/// # let window = winit::window::WindowBuilder::new().build(&event_loop).unwrap();
+///
+/// let instance = Instance::new_with_surface(&window, InstanceDesc::default()).unwrap();
+/// let surface = Surface::new(&instance, &window).unwrap();
+/// ```
 pub struct Surface {
-    pub(crate) surface: ash::extensions::khr::Surface,
-    pub(crate) surface_khr: SurfaceKHR,
+    pub(crate) inner: Arc<RawSurface>,
+}
+
+/// Owns the `VkSurfaceKHR`
+///
+/// Swapchains hold an `Arc` of it because a surface must outlive its swapchains.
+pub(crate) struct RawSurface {
+    pub(crate) loader: ash::extensions::khr::Surface,
+    pub(crate) khr: SurfaceKHR,
+    pub(crate) _instance: Arc<RawInstance>,
+}
+
+impl Drop for RawSurface {
+    fn drop(&mut self) {
+        unsafe {
+            self.loader.destroy_surface(self.khr, None);
+        }
+    }
 }
 
 impl Surface {
-    #[cfg(feature = "win32_surface")]
-    pub fn create_for_win32(instance: &Instance, hwnd: HWND, hinstance: HINSTANCE) -> Self {
-        use ash::vk::Win32SurfaceCreateInfoKHR;
-
-        let create_info = Win32SurfaceCreateInfoKHR::builder()
-            .hinstance(hinstance)
-            .hwnd(hwnd)
-            .build();
-        let win32_surface =
-            ash::extensions::khr::Win32Surface::new(&instance.entry, &instance.instance);
-        let surface = ash::extensions::khr::Surface::new(&instance.entry, &instance.instance);
-        let surface_khr = match unsafe { win32_surface.create_win32_surface(&create_info, None) } {
-            Ok(s) => s,
-            Err(_) => panic!("Err"),
-        };
+    /// Create a surface for a window
+    ///
+    /// The platform is chosen from the window handle, so any window implementing
+    /// `raw-window-handle` (e.g. a winit window) can be used.
+    ///
+    /// # Arguments
+    ///
+    /// * `instance` - Instance created with `Instance::new_with_surface` for the same display.
+    /// * `window` - The window to present to.
+    pub fn new(
+        instance: &Instance,
+        window: &(impl HasRawWindowHandle + HasRawDisplayHandle),
+    ) -> Result<Self> {
+        let required = ash_window::enumerate_required_extensions(window.raw_display_handle())
+            .map_err(|e| Error::from_vk(e, "ash_window::enumerate_required_extensions"))?;
+        let required: Vec<_> = required
+            .iter()
+            .map(|name| unsafe { CStr::from_ptr(*name) })
+            .collect();
+        instance.require_extensions(&required, "Surface::new")?;
+        let khr = unsafe {
+            ash_window::create_surface(
+                &instance.entry,
+                &instance.instance,
+                window.raw_display_handle(),
+                window.raw_window_handle(),
+                None,
+            )
+        }
+        .map_err(|e| Error::from_vk(e, "ash_window::create_surface"))?;
+        Ok(Self::from_khr(instance, khr))
+    }
 
+    pub(crate) fn from_khr(instance: &Instance, khr: SurfaceKHR) -> Self {
         Self {
-            surface,
-            surface_khr,
+            inner: Arc::new(RawSurface {
+                loader: ash::extensions::khr::Surface::new(&instance.entry, &instance.instance),
+                khr,
+                _instance: instance.instance.clone(),
+            }),
         }
     }
 }
@@ -0,0 +1,40 @@
+use std::ffi::c_void;
+
+use ash::vk::WaylandSurfaceCreateInfoKHR;
+
+use crate::{Error, Instance, Result, Surface};
+
+/// `wl_display*` of Wayland
+pub type WlDisplay = *mut c_void;
+/// `wl_surface*` of Wayland
+pub type WlSurface = *mut c_void;
+
+impl Surface {
+    /// Create a surface for a Wayland surface
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - Connection to the compositor.
+    /// * `surface` - Surface of the window.
+    pub fn create_for_wayland(
+        instance: &Instance,
+        display: WlDisplay,
+        surface: WlSurface,
+    ) -> Result<Self> {
+        instance.require_extensions(
+            &[
+                ash::extensions::khr::Surface::name(),
+                ash::extensions::khr::WaylandSurface::name(),
+            ],
+            "Surface::create_for_wayland",
+        )?;
+        let create_info = WaylandSurfaceCreateInfoKHR::builder()
+            .display(display)
+            .surface(surface)
+            .build();
+        let loader = ash::extensions::khr::WaylandSurface::new(&instance.entry, &instance.instance);
+        let khr = unsafe { loader.create_wayland_surface(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateWaylandSurfaceKHR"))?;
+        Ok(Self::from_khr(instance, khr))
+    }
+}
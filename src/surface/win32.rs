@@ -0,0 +1,34 @@
+use std::ffi::c_void;
+
+use ash::vk::Win32SurfaceCreateInfoKHR;
+
+use crate::{Error, Instance, Result, Surface};
+
+pub type HWND = *const c_void;
+pub type HINSTANCE = *const c_void;
+
+impl Surface {
+    /// Create a surface for a Win32 window
+    ///
+    /// # Arguments
+    ///
+    /// * `hwnd` - Handle of the window.
+    /// * `hinstance` - Handle of the module that created the window.
+    pub fn create_for_win32(instance: &Instance, hwnd: HWND, hinstance: HINSTANCE) -> Result<Self> {
+        instance.require_extensions(
+            &[
+                ash::extensions::khr::Surface::name(),
+                ash::extensions::khr::Win32Surface::name(),
+            ],
+            "Surface::create_for_win32",
+        )?;
+        let create_info = Win32SurfaceCreateInfoKHR::builder()
+            .hinstance(hinstance)
+            .hwnd(hwnd)
+            .build();
+        let loader = ash::extensions::khr::Win32Surface::new(&instance.entry, &instance.instance);
+        let khr = unsafe { loader.create_win32_surface(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateWin32SurfaceKHR"))?;
+        Ok(Self::from_khr(instance, khr))
+    }
+}
@@ -0,0 +1,40 @@
+use std::ffi::c_void;
+
+use ash::vk::XcbSurfaceCreateInfoKHR;
+
+use crate::{Error, Instance, Result, Surface};
+
+/// `xcb_connection_t*` of XCB
+pub type XcbConnection = *mut c_void;
+/// `xcb_window_t` of XCB
+pub type XcbWindow = u32;
+
+impl Surface {
+    /// Create a surface for an XCB window
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - Connection to the X server.
+    /// * `window` - The window.
+    pub fn create_for_xcb(
+        instance: &Instance,
+        connection: XcbConnection,
+        window: XcbWindow,
+    ) -> Result<Self> {
+        instance.require_extensions(
+            &[
+                ash::extensions::khr::Surface::name(),
+                ash::extensions::khr::XcbSurface::name(),
+            ],
+            "Surface::create_for_xcb",
+        )?;
+        let create_info = XcbSurfaceCreateInfoKHR::builder()
+            .connection(connection)
+            .window(window)
+            .build();
+        let loader = ash::extensions::khr::XcbSurface::new(&instance.entry, &instance.instance);
+        let khr = unsafe { loader.create_xcb_surface(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateXcbSurfaceKHR"))?;
+        Ok(Self::from_khr(instance, khr))
+    }
+}
@@ -0,0 +1,40 @@
+use std::ffi::{c_ulong, c_void};
+
+use ash::vk::XlibSurfaceCreateInfoKHR;
+
+use crate::{Error, Instance, Result, Surface};
+
+/// `Display*` of Xlib
+pub type XlibDisplay = *mut c_void;
+/// `Window` of Xlib
+pub type XlibWindow = c_ulong;
+
+impl Surface {
+    /// Create a surface for an Xlib window
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - Connection to the X server.
+    /// * `window` - The window.
+    pub fn create_for_xlib(
+        instance: &Instance,
+        display: XlibDisplay,
+        window: XlibWindow,
+    ) -> Result<Self> {
+        instance.require_extensions(
+            &[
+                ash::extensions::khr::Surface::name(),
+                ash::extensions::khr::XlibSurface::name(),
+            ],
+            "Surface::create_for_xlib",
+        )?;
+        let create_info = XlibSurfaceCreateInfoKHR::builder()
+            .dpy(display as *mut ash::vk::Display)
+            .window(window)
+            .build();
+        let loader = ash::extensions::khr::XlibSurface::new(&instance.entry, &instance.instance);
+        let khr = unsafe { loader.create_xlib_surface(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateXlibSurfaceKHR"))?;
+        Ok(Self::from_khr(instance, khr))
+    }
+}
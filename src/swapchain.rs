@@ -1,55 +1,537 @@
 use ash::vk::{
-    ComponentMapping, ComponentSwizzle, ImageAspectFlags, ImageSubresourceRange,
-    ImageViewCreateInfo, ImageViewType, SurfaceFormatKHR, SwapchainKHR,
+    ComponentMapping, ComponentSwizzle, Format, ImageAspectFlags, ImageSubresourceRange,
+    ImageViewCreateInfo, ImageViewType, SharingMode, SurfaceCapabilitiesKHR,
+    SwapchainCreateInfoKHR, SwapchainKHR,
 };
 
-use crate::{Device, GMResult, ImageView};
+pub use ash::vk::{
+    ColorSpaceKHR, CompositeAlphaFlagsKHR, Extent2D, ImageUsageFlags, PresentModeKHR,
+    SurfaceFormatKHR,
+};
+
+use std::sync::Arc;
+
+use crate::{
+    Device, Error, ErrorKind, Fence, ImageView, Instance, RawDevice, RawSurface, Result, Semaphore,
+    Surface, GPU,
+};
+
+/// Result of [Device::acquire_next_image]
+///
+/// # Value Meaning
+/// * `Ok` - Index of the acquired image.
+/// * `Suboptimal` - Index of the acquired image. It can still be presented, but the swapchain
+///   no longer matches the surface and should be recreated.
+/// * `OutOfDate` - No image was acquired. The swapchain must be recreated with [Swapchain::recreate].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcquireResult {
+    Ok(usize),
+    Suboptimal(usize),
+    OutOfDate,
+}
+
+impl AcquireResult {
+    /// Index of the acquired image, if any.
+    pub fn index(self) -> Option<usize> {
+        match self {
+            AcquireResult::Ok(i) | AcquireResult::Suboptimal(i) => Some(i),
+            AcquireResult::OutOfDate => None,
+        }
+    }
+}
+
+/// Result of [Queue::present](crate::Queue::present)
+///
+/// # Value Meaning
+/// * `Ok` - The image was presented.
+/// * `Suboptimal` - The image was presented, but the swapchain should be recreated.
+/// * `OutOfDate` - The image was not presented. The swapchain must be recreated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentResult {
+    Ok,
+    Suboptimal,
+    OutOfDate,
+}
+
+impl PresentResult {
+    /// Returns true if the swapchain should be recreated.
+    pub fn needs_recreate(self) -> bool {
+        self != PresentResult::Ok
+    }
+}
+
+/// Description for Swapchain Creation
+///
+/// Preferences are tried in order, and the first one the surface supports is chosen.
+/// [Surface::support] reports what the surface supports, and the getters of [Swapchain]
+/// report what was chosen.
+///
+/// # Example
+/// ```no_run
+/// use gallium::{Device, ImageUsageFlags, Instance, PresentModeKHR, Surface, SwapchainDesc, GPU};
+/// # fn example(instance: &Instance, device: &Device, gpu: &GPU, surface: &Surface) {
+///
+/// // Low latency without tearing, with screenshots
+/// let swapchain = device
+///     .create_swapchain_with_desc(
+///         instance,
+///         gpu,
+///         surface,
+///         &SwapchainDesc {
+///             present_modes: vec![PresentModeKHR::MAILBOX, PresentModeKHR::FIFO],
+///             usage: ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC,
+///             ..Default::default()
+///         },
+///     )
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwapchainDesc {
+    /// Preferred present modes. `FIFO` is used if none is supported, as it is always available.
+    pub present_modes: Vec<PresentModeKHR>,
+    /// Preferred formats. The first format the surface reports is used if none is supported.
+    pub formats: Vec<SurfaceFormatKHR>,
+    /// Preferred composite alpha modes. The first supported mode is used if none is supported.
+    pub composite_alpha: Vec<CompositeAlphaFlagsKHR>,
+    /// Usage of the swapchain images. All flags must be supported by the surface.
+    pub usage: ImageUsageFlags,
+    /// Desired number of images, clamped to what the surface supports.
+    pub image_count: u32,
+    /// Size of the images, used when the surface does not dictate it.
+    pub extent: Option<Extent2D>,
+}
+
+impl Default for SwapchainDesc {
+    fn default() -> Self {
+        let srgb = |format| SurfaceFormatKHR {
+            format,
+            color_space: ColorSpaceKHR::SRGB_NONLINEAR,
+        };
+        Self {
+            present_modes: vec![PresentModeKHR::FIFO],
+            formats: vec![
+                srgb(Format::B8G8R8A8_SRGB),
+                srgb(Format::R8G8B8A8_SRGB),
+                srgb(Format::B8G8R8A8_UNORM),
+                srgb(Format::R8G8B8A8_UNORM),
+            ],
+            composite_alpha: vec![CompositeAlphaFlagsKHR::OPAQUE],
+            usage: ImageUsageFlags::COLOR_ATTACHMENT,
+            image_count: 3,
+            extent: None,
+        }
+    }
+}
+
+/// What a surface supports on a GPU
+///
+/// It can be obtained with support from Surface.
+#[derive(Clone, Debug)]
+pub struct SurfaceSupport {
+    pub formats: Vec<SurfaceFormatKHR>,
+    pub present_modes: Vec<PresentModeKHR>,
+    pub min_image_count: u32,
+    /// `None` if there is no limit.
+    pub max_image_count: Option<u32>,
+    /// `None` if the size is decided by the swapchain.
+    pub current_extent: Option<Extent2D>,
+    pub min_extent: Extent2D,
+    pub max_extent: Extent2D,
+    pub composite_alpha: CompositeAlphaFlagsKHR,
+    pub usage: ImageUsageFlags,
+}
+
+impl Surface {
+    /// Query the formats, present modes and limits the surface supports on `gpu`.
+    pub fn support(&self, gpu: &GPU) -> Result<SurfaceSupport> {
+        let capabilities = surface_capabilities(&self.inner, gpu.device)?;
+        let formats = unsafe {
+            self.inner
+                .loader
+                .get_physical_device_surface_formats(gpu.device, self.inner.khr)
+        }
+        .map_err(|e| Error::from_vk(e, "vkGetPhysicalDeviceSurfaceFormatsKHR"))?;
+        let present_modes = unsafe {
+            self.inner
+                .loader
+                .get_physical_device_surface_present_modes(gpu.device, self.inner.khr)
+        }
+        .map_err(|e| Error::from_vk(e, "vkGetPhysicalDeviceSurfacePresentModesKHR"))?;
+        Ok(SurfaceSupport {
+            formats,
+            present_modes,
+            min_image_count: capabilities.min_image_count,
+            max_image_count: (capabilities.max_image_count != 0)
+                .then_some(capabilities.max_image_count),
+            current_extent: (capabilities.current_extent.width != u32::MAX)
+                .then_some(capabilities.current_extent),
+            min_extent: capabilities.min_image_extent,
+            max_extent: capabilities.max_image_extent,
+            composite_alpha: capabilities.supported_composite_alpha,
+            usage: capabilities.supported_usage_flags,
+        })
+    }
+}
 
 pub struct Swapchain {
     pub(crate) inner: ash::extensions::khr::Swapchain,
     pub(crate) khr: SwapchainKHR,
+    pub(crate) config: SwapchainConfig,
+    pub(crate) extent: Extent2D,
+    pub(crate) image_views: Vec<ImageView>,
+    pub(crate) device: Arc<RawDevice>,
+    pub(crate) surface: Arc<RawSurface>,
+}
+
+/// Settings chosen from a [SwapchainDesc], kept for recreation.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SwapchainConfig {
     pub(crate) format: SurfaceFormatKHR,
+    pub(crate) present_mode: PresentModeKHR,
+    pub(crate) composite_alpha: CompositeAlphaFlagsKHR,
+    pub(crate) usage: ImageUsageFlags,
+    pub(crate) image_count: u32,
+}
+
+impl Drop for Swapchain {
+    fn drop(&mut self) {
+        // The views must be destroyed before the images they refer to.
+        self.image_views.clear();
+        unsafe {
+            self.inner.destroy_swapchain(self.khr, None);
+        }
+    }
 }
 
 impl Swapchain {
-    pub fn get_image(&self, device: &Device) -> Result<Vec<ImageView>, GMResult> {
-        let images = match unsafe { self.inner.get_swapchain_images(self.khr) } {
-            Ok(i) => i,
-            Err(_) => panic!("Err"),
-        };
+    /// Format of the swapchain images.
+    ///
+    /// Use it as the format of the render pass attachment that renders to the swapchain.
+    pub fn format(&self) -> Format {
+        self.config.format.format
+    }
 
-        let mut image_views = vec![];
-        for image in images {
-            let create_info = ImageViewCreateInfo::builder()
-                .image(image)
-                .view_type(ImageViewType::TYPE_2D)
-                .format(self.format.format)
-                .components(
-                    ComponentMapping::builder()
-                        .a(ComponentSwizzle::IDENTITY)
-                        .r(ComponentSwizzle::IDENTITY)
-                        .g(ComponentSwizzle::IDENTITY)
-                        .b(ComponentSwizzle::IDENTITY)
-                        .build(),
-                )
-                .subresource_range(
-                    ImageSubresourceRange::builder()
-                        .aspect_mask(ImageAspectFlags::COLOR)
-                        .base_mip_level(0)
-                        .level_count(1)
-                        .base_array_layer(0)
-                        .layer_count(1)
-                        .build(),
-                )
-                .build();
-            match unsafe { device.inner.create_image_view(&create_info, None) } {
-                Ok(inner) => {
-                    image_views.push(ImageView { inner });
-                }
-                Err(_) => panic!("Err"),
-            }
+    pub fn color_space(&self) -> ColorSpaceKHR {
+        self.config.format.color_space
+    }
+
+    pub fn present_mode(&self) -> PresentModeKHR {
+        self.config.present_mode
+    }
+
+    pub fn composite_alpha(&self) -> CompositeAlphaFlagsKHR {
+        self.config.composite_alpha
+    }
+
+    pub fn usage(&self) -> ImageUsageFlags {
+        self.config.usage
+    }
+
+    /// Number of swapchain images. It can be larger than the requested count.
+    pub fn image_count(&self) -> usize {
+        self.image_views.len()
+    }
+
+    /// Size of the swapchain images.
+    pub fn extent(&self) -> Extent2D {
+        self.extent
+    }
+
+    /// Views of the swapchain images, in the order of the indices returned by acquire_next_image.
+    ///
+    /// They are replaced by [Swapchain::recreate], after which frame buffers must be created again.
+    pub fn image_views(&self) -> &[ImageView] {
+        &self.image_views
+    }
+
+    /// Create new views of the swapchain images.
+    pub fn get_image(&self, device: &Device) -> Result<Vec<ImageView>> {
+        create_image_views(&device.inner, &self.inner, self.khr, self.format())
+    }
+
+    /// Recreate the swapchain, e.g. after the window was resized or a result was `OutOfDate`
+    ///
+    /// Waits until the device is idle, then creates a new swapchain from the old one and
+    /// rebuilds [Swapchain::image_views]. The format and the other settings are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_extent` - Size of the window. Surfaces that dictate their size ignore it.
+    pub fn recreate(&mut self, new_extent: Extent2D) -> Result<()> {
+        unsafe { self.device.device_wait_idle() }
+            .map_err(|e| Error::from_vk(e, "vkDeviceWaitIdle"))?;
+        self.image_views.clear();
+
+        let old = self.khr;
+        let (khr, extent) = create_khr(
+            &self.device,
+            &self.inner,
+            &self.surface,
+            &self.config,
+            Some(new_extent),
+            old,
+        )?;
+        unsafe { self.inner.destroy_swapchain(old, None) };
+        self.khr = khr;
+        self.extent = extent;
+        self.image_views = create_image_views(&self.device, &self.inner, khr, self.format())?;
+        Ok(())
+    }
+}
+
+fn surface_capabilities(
+    surface: &RawSurface,
+    gpu: ash::vk::PhysicalDevice,
+) -> Result<SurfaceCapabilitiesKHR> {
+    unsafe {
+        surface
+            .loader
+            .get_physical_device_surface_capabilities(gpu, surface.khr)
+    }
+    .map_err(|e| Error::from_vk(e, "vkGetPhysicalDeviceSurfaceCapabilitiesKHR"))
+}
+
+/// Choose the settings of `desc` that `support` allows.
+fn choose_config(desc: &SwapchainDesc, support: &SurfaceSupport) -> Result<SwapchainConfig> {
+    let format = desc
+        .formats
+        .iter()
+        .find(|f| support.formats.contains(f))
+        .or_else(|| support.formats.first())
+        .copied()
+        .ok_or_else(|| {
+            Error::new(ErrorKind::Unsupported, "Device::create_swapchain")
+                .with_context("the surface reports no formats")
+        })?;
+    let present_mode = desc
+        .present_modes
+        .iter()
+        .find(|m| support.present_modes.contains(m))
+        .copied()
+        .unwrap_or(PresentModeKHR::FIFO);
+    let composite_alpha = desc
+        .composite_alpha
+        .iter()
+        .find(|a| support.composite_alpha.contains(**a))
+        .copied()
+        .or_else(|| {
+            [
+                CompositeAlphaFlagsKHR::OPAQUE,
+                CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+                CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+                CompositeAlphaFlagsKHR::INHERIT,
+            ]
+            .into_iter()
+            .find(|a| support.composite_alpha.contains(*a))
+        })
+        .ok_or_else(|| {
+            Error::new(ErrorKind::Unsupported, "Device::create_swapchain")
+                .with_context("the surface reports no composite alpha modes")
+        })?;
+    if !support.usage.contains(desc.usage) {
+        return Err(
+            Error::new(ErrorKind::Unsupported, "Device::create_swapchain").with_context(format!(
+                "usage {:?} is not supported, the surface supports {:?}",
+                desc.usage, support.usage
+            )),
+        );
+    }
+    let image_count = desc
+        .image_count
+        .max(support.min_image_count)
+        .min(support.max_image_count.unwrap_or(u32::MAX));
+    Ok(SwapchainConfig {
+        format,
+        present_mode,
+        composite_alpha,
+        usage: desc.usage,
+        image_count,
+    })
+}
+
+fn create_khr(
+    device: &RawDevice,
+    loader: &ash::extensions::khr::Swapchain,
+    surface: &RawSurface,
+    config: &SwapchainConfig,
+    extent: Option<Extent2D>,
+    old_swapchain: SwapchainKHR,
+) -> Result<(SwapchainKHR, Extent2D)> {
+    let surface_capabilities = surface_capabilities(surface, device.gpu)?;
+
+    // A current extent of u32::MAX means the size is decided by the swapchain.
+    let extent = if surface_capabilities.current_extent.width != u32::MAX {
+        surface_capabilities.current_extent
+    } else {
+        let min = surface_capabilities.min_image_extent;
+        let max = surface_capabilities.max_image_extent;
+        let requested = extent.unwrap_or(min);
+        Extent2D {
+            width: requested.width.clamp(min.width, max.width),
+            height: requested.height.clamp(min.height, max.height),
         }
+    };
+    if extent.width == 0 || extent.height == 0 {
+        return Err(Error::new(ErrorKind::InvalidValue, "vkCreateSwapchainKHR")
+            .with_context("the surface has a zero size, e.g. the window is minimized"));
+    }
+
+    let create_info = SwapchainCreateInfoKHR::builder()
+        .surface(surface.khr)
+        .min_image_count(config.image_count)
+        .image_format(config.format.format)
+        .image_color_space(config.format.color_space)
+        .image_extent(extent)
+        .image_array_layers(1)
+        .image_usage(config.usage)
+        .image_sharing_mode(SharingMode::EXCLUSIVE)
+        .pre_transform(surface_capabilities.current_transform)
+        .composite_alpha(config.composite_alpha)
+        .present_mode(config.present_mode)
+        .clipped(true)
+        .old_swapchain(old_swapchain)
+        .build();
+    let khr = unsafe { loader.create_swapchain(&create_info, None) }.map_err(|e| {
+        Error::from_vk(e, "vkCreateSwapchainKHR").with_context(format!("{:?}", config))
+    })?;
+    Ok((khr, extent))
+}
+
+fn create_image_views(
+    device: &Arc<RawDevice>,
+    loader: &ash::extensions::khr::Swapchain,
+    khr: SwapchainKHR,
+    format: Format,
+) -> Result<Vec<ImageView>> {
+    let images = unsafe { loader.get_swapchain_images(khr) }
+        .map_err(|e| Error::from_vk(e, "vkGetSwapchainImagesKHR"))?;
+
+    let mut image_views = vec![];
+    for image in images {
+        let create_info = ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(ImageViewType::TYPE_2D)
+            .format(format)
+            .components(
+                ComponentMapping::builder()
+                    .a(ComponentSwizzle::IDENTITY)
+                    .r(ComponentSwizzle::IDENTITY)
+                    .g(ComponentSwizzle::IDENTITY)
+                    .b(ComponentSwizzle::IDENTITY)
+                    .build(),
+            )
+            .subresource_range(
+                ImageSubresourceRange::builder()
+                    .aspect_mask(ImageAspectFlags::COLOR)
+                    .base_mip_level(0)
+                    .level_count(1)
+                    .base_array_layer(0)
+                    .layer_count(1)
+                    .build(),
+            )
+            .build();
+        let inner = unsafe { device.create_image_view(&create_info, None) }
+            .map_err(|e| Error::from_vk(e, "vkCreateImageView"))?;
+        image_views.push(ImageView {
+            inner,
+            device: device.clone(),
+        });
+    }
+
+    Ok(image_views)
+}
+
+impl Device {
+    /// Create a swapchain with the default [SwapchainDesc].
+    pub fn create_swapchain(
+        &self,
+        instance: &Instance,
+        device: &Device,
+        gpu: &GPU,
+        surface: &Surface,
+    ) -> Result<Swapchain> {
+        device.create_swapchain_with_desc(instance, gpu, surface, &SwapchainDesc::default())
+    }
+
+    /// Create a swapchain
+    ///
+    /// # Arguments
+    ///
+    /// * `gpu` - GPU the device was created from.
+    /// * `surface` - Surface to present to.
+    /// * `desc` - Preferred settings of the swapchain.
+    pub fn create_swapchain_with_desc(
+        &self,
+        instance: &Instance,
+        gpu: &GPU,
+        surface: &Surface,
+        desc: &SwapchainDesc,
+    ) -> Result<Swapchain> {
+        let support = surface.support(gpu)?;
+        let config = choose_config(desc, &support)?;
+        let inner = ash::extensions::khr::Swapchain::new(&instance.instance, &self.inner);
+        let (khr, extent) = create_khr(
+            &self.inner,
+            &inner,
+            &surface.inner,
+            &config,
+            desc.extent,
+            SwapchainKHR::null(),
+        )?;
+        let image_views = match create_image_views(&self.inner, &inner, khr, config.format.format) {
+            Ok(v) => v,
+            Err(e) => {
+                unsafe { inner.destroy_swapchain(khr, None) };
+                return Err(e);
+            }
+        };
+        Ok(Swapchain {
+            inner,
+            khr,
+            config,
+            extent,
+            image_views,
+            device: self.inner.clone(),
+            surface: surface.inner.clone(),
+        })
+    }
 
-        Ok(image_views)
+    /// Acquire the index of the next swapchain image to render to
+    ///
+    /// # Arguments
+    ///
+    /// * `semaphore` - Signaled when the image can be rendered to.
+    ///   Wait on it in [SubmitDesc::wait](crate::SubmitDesc::wait).
+    /// * `fence` - Signaled when the image can be rendered to.
+    ///
+    /// At least one of `semaphore` and `fence` must be given.
+    /// When the result is `AcquireResult::OutOfDate`, neither is signaled.
+    pub fn acquire_next_image(
+        &self,
+        swapchain: &Swapchain,
+        semaphore: Option<&Semaphore>,
+        fence: Option<&Fence>,
+    ) -> Result<AcquireResult> {
+        if semaphore.is_none() && fence.is_none() {
+            return Err(
+                Error::new(ErrorKind::InvalidValue, "Device::acquire_next_image")
+                    .with_context("either a semaphore or a fence is required"),
+            );
+        }
+        let result = unsafe {
+            swapchain.inner.acquire_next_image(
+                swapchain.khr,
+                1000000000,
+                semaphore.map_or(ash::vk::Semaphore::null(), |s| s.inner),
+                fence.map_or(ash::vk::Fence::null(), |f| f.inner),
+            )
+        };
+        match result {
+            Ok((index, false)) => Ok(AcquireResult::Ok(index as usize)),
+            Ok((index, true)) => Ok(AcquireResult::Suboptimal(index as usize)),
+            Err(ash::vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(AcquireResult::OutOfDate),
+            Err(e) => Err(Error::from_vk(e, "vkAcquireNextImageKHR")),
+        }
     }
 }
@@ -0,0 +1,264 @@
+use ash::vk::{
+    AccessFlags, DependencyFlags, Extent3D, Format, FormatFeatureFlags, ImageLayout,
+    ImageUsageFlags, PipelineStageFlags,
+};
+
+use crate::{
+    color_copy_region, layout_barrier, texel_size, BufferDesc, BufferUsageFlags, Device, Error,
+    ErrorKind, Image, ImageDesc, MemoryLocation, Queue, Result,
+};
+
+impl Device {
+    /// Create a texture from pixel data
+    ///
+    /// The pixels are uploaded through a staging buffer and the call waits until the upload has
+    /// finished. The image uses OPTIMAL tiling and is left in `SHADER_READ_ONLY_OPTIMAL`,
+    /// ready to be bound with `DescriptorSet::write_combined_image_sampler`.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - Queue that performs the upload.
+    /// * `format` - Format of the texels, e.g. `Format::R8G8B8A8_SRGB`. Compressed and depth formats are not supported.
+    /// * `data` - Tightly packed rows of texels, `width * height * texel size` bytes.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gallium::{Device, Format, Queue};
+    /// # fn example(device: &Device, queue: &Queue) {
+    ///
+    /// // 2x2 checkerboard
+    /// let pixels: [u8; 16] = [
+    ///     255, 255, 255, 255, 0, 0, 0, 255,
+    ///     0, 0, 0, 255, 255, 255, 255, 255,
+    /// ];
+    /// let texture = device
+    ///     .create_texture(queue, 2, 2, Format::R8G8B8A8_UNORM, &pixels)
+    ///     .unwrap();
+    /// let view = texture.create_image_view(device).unwrap();
+    /// # }
+    /// ```
+    pub fn create_texture(
+        &self,
+        queue: &Queue,
+        width: u32,
+        height: u32,
+        format: Format,
+        data: &[u8],
+    ) -> Result<Image> {
+        let texel_size = texel_size(format).ok_or_else(|| {
+            Error::new(ErrorKind::FormatNotSupported, "Device::create_texture")
+                .with_context(format!("{:?} cannot be uploaded", format))
+        })?;
+        let size = width as u64 * height as u64 * texel_size;
+        if size == 0 || data.len() as u64 != size {
+            return Err(
+                Error::new(ErrorKind::InvalidValue, "Device::create_texture").with_context(
+                    format!(
+                        "{}x{} {:?} needs {} bytes, got {}",
+                        width,
+                        height,
+                        format,
+                        size,
+                        data.len()
+                    ),
+                ),
+            );
+        }
+        self.check_format_features(
+            format,
+            FormatFeatureFlags::SAMPLED_IMAGE | FormatFeatureFlags::TRANSFER_DST,
+            "Device::create_texture",
+        )?;
+
+        let mut staging = self.create_buffer(&BufferDesc {
+            size,
+            usage: BufferUsageFlags::TRANSFER_SRC,
+            location: MemoryLocation::CpuToGpu,
+        })?;
+        staging.write(0, data)?;
+
+        // TRANSFER_SRC allows `Image::read_pixels`.
+        let image = self.create_image_with_desc(&ImageDesc {
+            format,
+            extent: Extent3D {
+                width,
+                height,
+                depth: 1,
+            },
+            usage: ImageUsageFlags::TRANSFER_DST
+                | ImageUsageFlags::TRANSFER_SRC
+                | ImageUsageFlags::SAMPLED,
+            ..Default::default()
+        })?;
+
+        let to_transfer = layout_barrier(
+            image.inner,
+            ImageLayout::UNDEFINED,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            AccessFlags::empty(),
+            AccessFlags::TRANSFER_WRITE,
+        );
+        let to_shader_read = layout_barrier(
+            image.inner,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            AccessFlags::TRANSFER_WRITE,
+            AccessFlags::SHADER_READ,
+        );
+        let region = color_copy_region(width, height);
+
+        // The staging buffer must outlive the upload.
+        self.run_commands(queue, |command_buffer| unsafe {
+            self.inner.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TOP_OF_PIPE,
+                PipelineStageFlags::TRANSFER,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer],
+            );
+            self.inner.cmd_copy_buffer_to_image(
+                command_buffer,
+                staging.inner,
+                image.inner,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+            );
+            self.inner.cmd_pipeline_barrier(
+                command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::VERTEX_SHADER
+                    | PipelineStageFlags::FRAGMENT_SHADER
+                    | PipelineStageFlags::COMPUTE_SHADER,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_shader_read],
+            );
+        })?;
+
+        Ok(image)
+    }
+
+    /// Create a texture from a PNG file
+    ///
+    /// The image is converted to 8-bit RGBA and uploaded as `R8G8B8A8_SRGB`.
+    /// See [Device::create_texture].
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - Queue that performs the upload.
+    /// * `data` - Contents of the PNG file.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn example(device: &gallium::Device, queue: &gallium::Queue) {
+    /// let texture = device
+    ///     .create_texture_from_png(queue, &std::fs::read("texture.png").unwrap())
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "png")]
+    pub fn create_texture_from_png(&self, queue: &Queue, data: &[u8]) -> Result<Image> {
+        let (width, height, pixels) = decode_png(data)?;
+        self.create_texture(queue, width, height, Format::R8G8B8A8_SRGB, &pixels)
+    }
+
+    /// Create a texture from a JPEG file
+    ///
+    /// The image is converted to 8-bit RGBA and uploaded as `R8G8B8A8_SRGB`.
+    /// See [Device::create_texture].
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - Queue that performs the upload.
+    /// * `data` - Contents of the JPEG file. CMYK files are not supported.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn example(device: &gallium::Device, queue: &gallium::Queue) {
+    /// let texture = device
+    ///     .create_texture_from_jpeg(queue, &std::fs::read("texture.jpg").unwrap())
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "jpeg")]
+    pub fn create_texture_from_jpeg(&self, queue: &Queue, data: &[u8]) -> Result<Image> {
+        let (width, height, pixels) = decode_jpeg(data)?;
+        self.create_texture(queue, width, height, Format::R8G8B8A8_SRGB, &pixels)
+    }
+}
+
+/// Decode a PNG file into tightly packed 8-bit RGBA rows.
+#[cfg(feature = "png")]
+fn decode_png(data: &[u8]) -> Result<(u32, u32, Vec<u8>)> {
+    use png::{ColorType, Decoder, Transformations};
+
+    let decode_error = |e: png::DecodingError| {
+        Error::new(ErrorKind::InvalidValue, "Device::create_texture_from_png").with_source(e)
+    };
+    let mut decoder = Decoder::new(data);
+    // Palettes and low bit depths are expanded, 16-bit channels are reduced to 8 bits.
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(decode_error)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(decode_error)?;
+    buf.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        ColorType::Rgba => buf,
+        ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        ColorType::Grayscale => buf.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        ColorType::Indexed => {
+            return Err(
+                Error::new(ErrorKind::Unsupported, "Device::create_texture_from_png")
+                    .with_context("indexed colors were not expanded"),
+            )
+        }
+    };
+    Ok((info.width, info.height, pixels))
+}
+
+/// Decode a JPEG file into tightly packed 8-bit RGBA rows.
+#[cfg(feature = "jpeg")]
+fn decode_jpeg(data: &[u8]) -> Result<(u32, u32, Vec<u8>)> {
+    use jpeg_decoder::{Decoder, PixelFormat};
+
+    let mut decoder = Decoder::new(data);
+    let buf = decoder.decode().map_err(|e| {
+        Error::new(ErrorKind::InvalidValue, "Device::create_texture_from_jpeg").with_source(e)
+    })?;
+    let info = decoder.info().ok_or_else(|| {
+        Error::new(ErrorKind::InvalidValue, "Device::create_texture_from_jpeg")
+            .with_context("the file has no frame header")
+    })?;
+
+    let pixels = match info.pixel_format {
+        PixelFormat::RGB24 => buf
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        PixelFormat::L8 => buf.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        // 16-bit samples are in native byte order; keep their high byte.
+        PixelFormat::L16 => buf
+            .chunks_exact(2)
+            .map(|p| (u16::from_ne_bytes([p[0], p[1]]) >> 8) as u8)
+            .flat_map(|p| [p, p, p, 255])
+            .collect(),
+        PixelFormat::CMYK32 => {
+            return Err(
+                Error::new(ErrorKind::Unsupported, "Device::create_texture_from_jpeg")
+                    .with_context("CMYK images are not supported"),
+            )
+        }
+    };
+    Ok((info.width as u32, info.height as u32, pixels))
+}
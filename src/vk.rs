@@ -1,85 +1,54 @@
-pub const VK_ERROR_OUT_OF_HOST_MEMORY: i32 = -1;
-pub const VK_ERROR_OUT_OF_DEVICE_MEMORY: i32 = -2;
-pub const VK_ERROR_INITIALIZATION_FAILED: i32 = -3;
-pub const VK_ERROR_INCOMPATIBLE_DRIVER: i32 = -9;
-pub const VK_ERROR_EXTENSION_NOT_PRESENT: i32 = -7;
-pub const VK_ERROR_INVALID_EXTERNAL_HANDLE: i32 = -1000072003;
+use ash::vk::Result as VkResult;
 
-#[repr(i32)]
-pub enum VkResult {
-    VK_SUCCESS = 0,
-    VK_NOT_READY = 1,
-    VK_TIMEOUT = 2,
-    VK_EVENT_SET = 3,
-    VK_EVENT_RESET = 4,
-    VK_INCOMPLETE = 5,
-    VK_ERROR_OUT_OF_HOST_MEMORY = -1,
-    VK_ERROR_OUT_OF_DEVICE_MEMORY = -2,
-    VK_ERROR_INITIALIZATION_FAILED = -3,
-    VK_ERROR_DEVICE_LOST = -4,
-    VK_ERROR_MEMORY_MAP_FAILED = -5,
-    VK_ERROR_LAYER_NOT_PRESENT = -6,
-    VK_ERROR_EXTENSION_NOT_PRESENT = -7,
-    VK_ERROR_FEATURE_NOT_PRESENT = -8,
-    VK_ERROR_INCOMPATIBLE_DRIVER = -9,
-    VK_ERROR_TOO_MANY_OBJECTS = -10,
-    VK_ERROR_FORMAT_NOT_SUPPORTED = -11,
-    VK_ERROR_FRAGMENTED_POOL = -12,
-    VK_ERROR_UNKNOWN = -13,
-    // Provided by VK_VERSION_1_1
-    VK_ERROR_OUT_OF_POOL_MEMORY = -1000069000,
-    // Provided by VK_VERSION_1_1
-    VK_ERROR_INVALID_EXTERNAL_HANDLE = -1000072003,
-    // Provided by VK_VERSION_1_2
-    VK_ERROR_FRAGMENTATION = -1000161000,
-    // Provided by VK_VERSION_1_2
-    VK_ERROR_INVALID_OPAQUE_CAPTURE_ADDRESS = -1000257000,
-    // Provided by VK_VERSION_1_3
-    VK_PIPELINE_COMPILE_REQUIRED = 1000297000,
-    // Provided by VK_KHR_surface
-    VK_ERROR_SURFACE_LOST_KHR = -1000000000,
-    // Provided by VK_KHR_surface
-    VK_ERROR_NATIVE_WINDOW_IN_USE_KHR = -1000000001,
-    // Provided by VK_KHR_swapchain
-    VK_SUBOPTIMAL_KHR = 1000001003,
-    // Provided by VK_KHR_swapchain
-    VK_ERROR_OUT_OF_DATE_KHR = -1000001004,
-    // Provided by VK_KHR_display_swapchain
-    VK_ERROR_INCOMPATIBLE_DISPLAY_KHR = -1000003001,
-    // Provided by VK_EXT_debug_report
-    VK_ERROR_VALIDATION_FAILED_EXT = -1000011001,
-    // Provided by VK_NV_glsl_shader
-    VK_ERROR_INVALID_SHADER_NV = -1000012000,
-    // Provided by VK_KHR_video_queue
-    VK_ERROR_IMAGE_USAGE_NOT_SUPPORTED_KHR = -1000023000,
-    // Provided by VK_KHR_video_queue
-    VK_ERROR_VIDEO_PICTURE_LAYOUT_NOT_SUPPORTED_KHR = -1000023001,
-    // Provided by VK_KHR_video_queue
-    VK_ERROR_VIDEO_PROFILE_OPERATION_NOT_SUPPORTED_KHR = -1000023002,
-    // Provided by VK_KHR_video_queue
-    VK_ERROR_VIDEO_PROFILE_FORMAT_NOT_SUPPORTED_KHR = -1000023003,
-    // Provided by VK_KHR_video_queue
-    VK_ERROR_VIDEO_PROFILE_CODEC_NOT_SUPPORTED_KHR = -1000023004,
-    // Provided by VK_KHR_video_queue
-    VK_ERROR_VIDEO_STD_VERSION_NOT_SUPPORTED_KHR = -1000023005,
-    // Provided by VK_EXT_image_drm_format_modifier
-    VK_ERROR_INVALID_DRM_FORMAT_MODIFIER_PLANE_LAYOUT_EXT = -1000158000,
-    // Provided by VK_KHR_global_priority
-    VK_ERROR_NOT_PERMITTED_KHR = -1000174001,
-    // Provided by VK_EXT_full_screen_exclusive
-    VK_ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT = -1000255000,
-    // Provided by VK_KHR_deferred_host_operations
-    VK_THREAD_IDLE_KHR = 1000268000,
-    // Provided by VK_KHR_deferred_host_operations
-    VK_THREAD_DONE_KHR = 1000268001,
-    // Provided by VK_KHR_deferred_host_operations
-    VK_OPERATION_DEFERRED_KHR = 1000268002,
-    // Provided by VK_KHR_deferred_host_operations
-    VK_OPERATION_NOT_DEFERRED_KHR = 1000268003,
-    // Provided by VK_KHR_video_encode_queue
-    VK_ERROR_INVALID_VIDEO_STD_PARAMETERS_KHR = -1000299000,
-    // Provided by VK_EXT_image_compression_control
-    VK_ERROR_COMPRESSION_EXHAUSTED_EXT = -1000338000,
-    // Provided by VK_EXT_shader_object
-    VK_ERROR_INCOMPATIBLE_SHADER_BINARY_EXT = 1000482000,
+use crate::ErrorKind;
+
+/// Classify every `VkResult` code gallium can receive
+impl From<VkResult> for ErrorKind {
+    fn from(result: VkResult) -> Self {
+        match result {
+            VkResult::NOT_READY => ErrorKind::NotReady,
+            VkResult::TIMEOUT => ErrorKind::Timeout,
+            VkResult::INCOMPLETE => ErrorKind::Incomplete,
+            VkResult::ERROR_OUT_OF_HOST_MEMORY | VkResult::ERROR_OUT_OF_DEVICE_MEMORY => {
+                ErrorKind::OutOfMemory
+            }
+            VkResult::ERROR_OUT_OF_POOL_MEMORY
+            | VkResult::ERROR_FRAGMENTED_POOL
+            | VkResult::ERROR_FRAGMENTATION => ErrorKind::OutOfPoolMemory,
+            VkResult::ERROR_TOO_MANY_OBJECTS | VkResult::ERROR_COMPRESSION_EXHAUSTED_EXT => {
+                ErrorKind::TooManyObjects
+            }
+            VkResult::ERROR_INITIALIZATION_FAILED => ErrorKind::InitializationError,
+            VkResult::ERROR_DEVICE_LOST => ErrorKind::DeviceLost,
+            VkResult::ERROR_MEMORY_MAP_FAILED => ErrorKind::MemoryMapFailed,
+            VkResult::ERROR_LAYER_NOT_PRESENT => ErrorKind::LayerNotPresent,
+            VkResult::ERROR_EXTENSION_NOT_PRESENT => ErrorKind::ExtensionNotPresent,
+            VkResult::ERROR_FEATURE_NOT_PRESENT => ErrorKind::FeatureNotPresent,
+            VkResult::ERROR_INCOMPATIBLE_DRIVER => ErrorKind::IncompatibleDriver,
+            VkResult::ERROR_FORMAT_NOT_SUPPORTED => ErrorKind::FormatNotSupported,
+            VkResult::ERROR_IMAGE_USAGE_NOT_SUPPORTED_KHR
+            | VkResult::ERROR_VIDEO_PICTURE_LAYOUT_NOT_SUPPORTED_KHR
+            | VkResult::ERROR_VIDEO_PROFILE_OPERATION_NOT_SUPPORTED_KHR
+            | VkResult::ERROR_VIDEO_PROFILE_FORMAT_NOT_SUPPORTED_KHR
+            | VkResult::ERROR_VIDEO_PROFILE_CODEC_NOT_SUPPORTED_KHR
+            | VkResult::ERROR_VIDEO_STD_VERSION_NOT_SUPPORTED_KHR => ErrorKind::Unsupported,
+            VkResult::ERROR_INVALID_EXTERNAL_HANDLE
+            | VkResult::ERROR_INVALID_OPAQUE_CAPTURE_ADDRESS
+            | VkResult::ERROR_INVALID_SHADER_NV
+            | VkResult::ERROR_INVALID_DRM_FORMAT_MODIFIER_PLANE_LAYOUT_EXT
+            | VkResult::ERROR_INVALID_VIDEO_STD_PARAMETERS_KHR
+            | VkResult::ERROR_INCOMPATIBLE_SHADER_BINARY_EXT => ErrorKind::InvalidValue,
+            VkResult::ERROR_SURFACE_LOST_KHR
+            | VkResult::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT => ErrorKind::SurfaceLost,
+            VkResult::ERROR_NATIVE_WINDOW_IN_USE_KHR => ErrorKind::NativeWindowInUse,
+            VkResult::SUBOPTIMAL_KHR => ErrorKind::Suboptimal,
+            VkResult::ERROR_OUT_OF_DATE_KHR => ErrorKind::OutOfDate,
+            VkResult::ERROR_INCOMPATIBLE_DISPLAY_KHR => ErrorKind::IncompatibleDisplay,
+            VkResult::ERROR_VALIDATION_FAILED_EXT => ErrorKind::ValidationFailed,
+            VkResult::ERROR_NOT_PERMITTED_KHR => ErrorKind::NotPermitted,
+            // VK_ERROR_UNKNOWN and the non-error codes such as VK_EVENT_SET,
+            // VK_THREAD_IDLE_KHR or VK_PIPELINE_COMPILE_REQUIRED.
+            _ => ErrorKind::UnknownError,
+        }
+    }
 }
@@ -0,0 +1,30 @@
+//! Drives the acquire, render and present loop of `examples/headless.rs`.
+//!
+//! It needs a Vulkan driver with `VK_EXT_headless_surface` and is skipped without one.
+//! GPU-less machines can run it with lavapipe, e.g. on CI:
+//!
+//! ```sh
+//! VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json cargo test --test headless
+//! ```
+
+use gallium::{ErrorKind, Instance, InstanceDesc};
+
+#[path = "../examples/headless.rs"]
+mod headless;
+
+#[test]
+fn present_headless() {
+    match Instance::new_headless(InstanceDesc::default()) {
+        Err(e)
+            if matches!(
+                e.kind(),
+                ErrorKind::ExtensionNotPresent | ErrorKind::IncompatibleDriver
+            ) =>
+        {
+            eprintln!("skipping present_headless: {}", e);
+            return;
+        }
+        instance => drop(instance.unwrap()),
+    }
+    headless::main().unwrap();
+}